@@ -0,0 +1,155 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::api::server::{json_error, AppState};
+use crate::blockchain::events::{ChainEvent, EventFilter};
+
+/// How far behind the tip `from_block` may start. Replaying holds the chain's
+/// read lock, so older history must be paged through `GET /blocks` instead.
+pub const MAX_REPLAY_BLOCKS: u64 = 1000;
+
+/// Stream parameters shared by `/events` and `/ws`. With `from_block`, the
+/// events of the blocks from that index on are replayed before live ones.
+#[derive(Deserialize)]
+pub struct EventQuery {
+    #[serde(flatten)]
+    filter: EventFilter,
+    from_block: Option<u64>,
+}
+
+/// Subscribes to new events and collects the replayed ones under the same
+/// read lock, so no block can be appended in between and go missing. Fails
+/// with 400 when `from_block` is more than `MAX_REPLAY_BLOCKS` behind.
+async fn subscribe(
+    state: &AppState,
+    from_block: Option<u64>,
+) -> Result<(Vec<ChainEvent>, broadcast::Receiver<ChainEvent>), Response> {
+    let deckchain = state.deckchain.read().await;
+    let tip = deckchain.blockchain.get_blocks().len() as u64;
+    check_replay_distance(from_block, tip)
+        .map_err(|message| json_error(StatusCode::BAD_REQUEST, &message).into_response())?;
+    let receiver = deckchain.events.subscribe();
+    let history = from_block
+        .map(|from| ChainEvent::replay(&deckchain.blockchain, from))
+        .unwrap_or_default();
+    Ok((history, receiver))
+}
+
+fn check_replay_distance(from_block: Option<u64>, tip: u64) -> Result<(), String> {
+    match from_block {
+        Some(from) if tip.saturating_sub(from) > MAX_REPLAY_BLOCKS => Err(format!(
+            "from_block must be within {} blocks of the chain height {}; page through /blocks for older events",
+            MAX_REPLAY_BLOCKS, tip
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Replayed then live events passing `filter`. A subscriber that falls more
+/// than the channel's buffer behind has its stream ended, and is expected to
+/// reconnect and resume from the last block it completed.
+fn event_stream(
+    history: Vec<ChainEvent>,
+    receiver: broadcast::Receiver<ChainEvent>,
+    filter: EventFilter,
+) -> impl Stream<Item = ChainEvent> {
+    let live = BroadcastStream::new(receiver).map_while(|event| match event {
+        Ok(event) => Some(event),
+        Err(e) => {
+            tracing::warn!("Event subscriber dropped: {}", e);
+            None
+        }
+    });
+    tokio_stream::iter(history)
+        .chain(live)
+        .filter(move |event| event.matches(&filter))
+}
+
+/// Server-Sent Events. Each `BlockAdded` carries its block index as the
+/// event id, so a reconnecting `EventSource` resumes after the last complete
+/// block through `Last-Event-ID`.
+pub async fn sse_events(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<EventQuery>,
+) -> Response {
+    let last_event_id = headers
+        .get("Last-Event-ID")
+        .and_then(|id| id.to_str().ok())
+        .and_then(|id| id.parse::<u64>().ok());
+    let from_block = query.from_block.or(last_event_id.map(|id| id + 1));
+
+    let (history, receiver) = match subscribe(&state, from_block).await {
+        Ok(subscription) => subscription,
+        Err(response) => return response,
+    };
+    let stream = event_stream(history, receiver, query.filter).map(|event| {
+        let sse = Event::default()
+            .event(event.name())
+            .json_data(&event)
+            .expect("chain events must be serializable");
+        Ok::<_, Infallible>(match event {
+            ChainEvent::BlockAdded { block_index, .. } => sse.id(block_index.to_string()),
+            _ => sse,
+        })
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default()).into_response()
+}
+
+/// WebSocket stream sending each event as a JSON text message.
+pub async fn ws_events(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<EventQuery>,
+) -> Response {
+    let (history, receiver) = match subscribe(&state, query.from_block).await {
+        Ok(subscription) => subscription,
+        Err(response) => return response,
+    };
+    let events = event_stream(history, receiver, query.filter);
+    ws.on_upgrade(move |socket| forward_events(socket, events))
+        .into_response()
+}
+
+async fn forward_events(mut socket: WebSocket, events: impl Stream<Item = ChainEvent>) {
+    tokio::pin!(events);
+    loop {
+        tokio::select! {
+            event = events.next() => {
+                let Some(event) = event else { break };
+                let json = serde_json::to_string(&event).expect("chain events must be serializable");
+                if socket.send(Message::Text(json)).await.is_err() {
+                    break;
+                }
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_distance_is_capped() {
+        assert!(check_replay_distance(None, 5000).is_ok());
+        assert!(check_replay_distance(Some(0), MAX_REPLAY_BLOCKS).is_ok());
+        assert!(check_replay_distance(Some(4000), 5000).is_ok());
+        assert!(check_replay_distance(Some(u64::MAX), 5000).is_ok());
+        assert!(check_replay_distance(Some(0), MAX_REPLAY_BLOCKS + 1).is_err());
+    }
+}
@@ -1,49 +1,89 @@
 use std::sync::Arc;
 
+use axum::body::{to_bytes, Body};
 use axum::extract::{Request, State};
 use axum::http::StatusCode;
 use axum::middleware::Next;
-use axum::response::IntoResponse;
+use axum::response::{IntoResponse, Response};
 use axum::Json;
+use chrono::Utc;
 use serde::Serialize;
 
 use crate::api::server::AppState;
+use crate::auth::keys::{AuthorizedKey, Scope};
+use crate::auth::signing::SignedHeaders;
+
+/// Largest request body the middleware will buffer to check its signature.
+pub(crate) const MAX_SIGNED_BODY: usize = 16 * 1024 * 1024;
 
 #[derive(Serialize)]
 struct AuthError {
     error: String,
 }
 
+fn auth_error(status: StatusCode, message: &str) -> Response {
+    (
+        status,
+        Json(AuthError {
+            error: message.to_string(),
+        }),
+    )
+        .into_response()
+}
+
+/// The authorized key that signed a request, set by `require_auth`.
+#[derive(Clone, Debug)]
+pub struct AuthenticatedKey(pub AuthorizedKey);
+
+/// Accepts requests signed by an authorized, unexpired key (see
+/// `SignedHeaders`) whose timestamp is within the replay window and whose
+/// nonce the key has not used before.
 pub async fn require_auth(
     State(state): State<Arc<AppState>>,
     request: Request,
     next: Next,
-) -> impl IntoResponse {
-    let api_key = request
-        .headers()
-        .get("X-API-Key")
-        .and_then(|v| v.to_str().ok());
-
-    match api_key {
-        None => (
-            StatusCode::UNAUTHORIZED,
-            Json(AuthError {
-                error: "Missing X-API-Key header".to_string(),
-            }),
-        )
-            .into_response(),
-        Some(key) => {
-            if state.authorized_keys.is_key_authorized(key) {
-                next.run(request).await
-            } else {
-                (
-                    StatusCode::FORBIDDEN,
-                    Json(AuthError {
-                        error: "Invalid or expired API key".to_string(),
-                    }),
-                )
-                    .into_response()
-            }
-        }
+) -> Response {
+    let Some(signed) = SignedHeaders::from_headers(request.headers()) else {
+        return auth_error(StatusCode::UNAUTHORIZED, "Missing or malformed request signature headers");
+    };
+    let now = Utc::now().timestamp();
+    if !signed.is_fresh(now) {
+        return auth_error(StatusCode::UNAUTHORIZED, "Request timestamp is outside the replay window");
+    }
+
+    // Only bodies claimed by an authorized key are buffered.
+    let Some(key) = state.authorized_keys.current().authorized_key(&signed.public_key).cloned() else {
+        return auth_error(StatusCode::FORBIDDEN, "Unknown or expired key");
+    };
+
+    let (parts, body) = request.into_parts();
+    let Ok(body) = to_bytes(body, MAX_SIGNED_BODY).await else {
+        return auth_error(StatusCode::PAYLOAD_TOO_LARGE, "Request body too large");
+    };
+    let path = parts.uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+    if !signed.verify(parts.method.as_str(), path, &body) {
+        return auth_error(StatusCode::UNAUTHORIZED, "Invalid request signature");
+    }
+    if !state.nonces.check_and_insert(&signed.public_key, &signed.nonce, signed.timestamp, now) {
+        return auth_error(StatusCode::UNAUTHORIZED, "Request nonce has already been used");
+    }
+
+    let mut request = Request::from_parts(parts, Body::from(body));
+    request.extensions_mut().insert(AuthenticatedKey(key));
+    next.run(request).await
+}
+
+/// Rejects requests whose key lacks `scope`, naming the missing scope.
+/// Layered inside `require_auth`, so the signing key is already authenticated.
+pub async fn require_scope(State(scope): State<Scope>, request: Request, next: Next) -> Response {
+    let granted = request
+        .extensions()
+        .get::<AuthenticatedKey>()
+        .is_some_and(|key| key.0.has_scope(scope));
+
+    if granted {
+        next.run(request).await
+    } else {
+        auth_error(StatusCode::FORBIDDEN, &format!("Key lacks the '{}' scope", scope))
     }
 }
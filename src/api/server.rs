@@ -1,22 +1,31 @@
 use std::sync::Arc;
 
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
 use axum::http::StatusCode;
 use axum::middleware as axum_middleware;
+use axum::handler::Handler;
 use axum::response::IntoResponse;
-use axum::routing::get;
+use axum::routing::{get, post};
 use axum::{Json, Router};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 
-use crate::api::middleware::require_auth;
-use crate::auth::keys::AuthorizedKeys;
-use crate::blockchain::deckchain::DeckChain;
+use crate::api::events::{sse_events, ws_events};
+use crate::api::middleware::{require_auth, require_scope};
+use crate::auth::keys::Scope;
+use crate::auth::signing::NonceCache;
+use crate::auth::watch::{WatchedKeys, RELOAD_INTERVAL};
+use crate::blockchain::block::Block;
+use crate::blockchain::deckchain::{CardDetails, DeckChain};
+use crate::blockchain::transaction::{BlockTransaction, TransactionType};
+use crate::card::card::TradingCard;
 use crate::config::Config;
+use crate::error::DeckForgeError;
 
 pub struct AppState {
     pub deckchain: RwLock<DeckChain>,
-    pub authorized_keys: AuthorizedKeys,
+    pub authorized_keys: WatchedKeys,
+    pub nonces: NonceCache,
 }
 
 #[derive(Serialize)]
@@ -24,7 +33,7 @@ struct ApiErrorResponse {
     error: String,
 }
 
-fn json_error(status: StatusCode, message: &str) -> impl IntoResponse {
+pub(crate) fn json_error(status: StatusCode, message: &str) -> impl IntoResponse {
     (
         status,
         Json(ApiErrorResponse {
@@ -33,6 +42,112 @@ fn json_error(status: StatusCode, message: &str) -> impl IntoResponse {
     )
 }
 
+#[derive(Serialize)]
+struct TransactionReceipt {
+    transaction_id: String,
+    block_index: u64,
+}
+
+#[derive(Serialize)]
+struct OpenPackReceipt {
+    transaction_id: String,
+    block_index: u64,
+    cards: Vec<TradingCard>,
+}
+
+#[derive(Deserialize)]
+struct TransferRequest {
+    card_id: String,
+    sender: String,
+    receiver: String,
+    nonce: u64,
+    signature: String,
+}
+
+#[derive(Serialize)]
+struct WalletCards {
+    address: String,
+    count: usize,
+    cards: Vec<CardDetails>,
+}
+
+#[derive(Deserialize)]
+struct SeriesCardsQuery {
+    rarity: Option<u32>,
+    #[serde(rename = "type")]
+    card_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BlockPageQuery {
+    from: Option<u64>,
+    limit: Option<usize>,
+}
+
+/// One page of blocks. `next` is the `from` cursor of the following page,
+/// absent on the last page; `total` is the number of blocks in the chain.
+#[derive(Serialize)]
+struct BlockPage<'a> {
+    blocks: &'a [Block],
+    from: u64,
+    limit: usize,
+    total: u64,
+    next: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct OpenPackRequest {
+    series_id: String,
+    pack_id: String,
+    receiver: String,
+}
+
+fn error_status(error: &DeckForgeError) -> StatusCode {
+    match error {
+        DeckForgeError::SeriesNotFound { .. }
+        | DeckForgeError::PackNotFound { .. }
+        | DeckForgeError::BlockNotFound { .. }
+        | DeckForgeError::BlockHashNotFound { .. }
+        | DeckForgeError::UnknownTransaction { .. }
+        | DeckForgeError::CardNotFound { .. }
+        | DeckForgeError::TransactionNotFound { .. }
+        | DeckForgeError::NoReleasesFound => StatusCode::NOT_FOUND,
+        DeckForgeError::AlreadyReleased { .. }
+        | DeckForgeError::AlreadyMinted { .. }
+        | DeckForgeError::NotCardOwner { .. }
+        | DeckForgeError::InvalidNonce { .. }
+        | DeckForgeError::ShuffleNotCommitted { .. }
+        | DeckForgeError::ShuffleCommitmentMismatch { .. }
+        | DeckForgeError::PackSoldOut { .. } => StatusCode::CONFLICT,
+        DeckForgeError::InvalidSignature { .. }
+        | DeckForgeError::Validation { .. }
+        | DeckForgeError::InvalidSeries { .. }
+        | DeckForgeError::InvalidReceiver { .. }
+        | DeckForgeError::NoMerkleRoot { .. }
+        | DeckForgeError::Json(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn write_error(error: DeckForgeError) -> axum::response::Response {
+    tracing::warn!("Write rejected: {}", error);
+    json_error(error_status(&error), &error.to_string()).into_response()
+}
+
+/// The receipt for the first transaction of the most recent block. Only
+/// meaningful while the caller still holds the write lock that appended it.
+fn last_receipt(deckchain: &DeckChain) -> TransactionReceipt {
+    let block = deckchain
+        .blockchain
+        .blocks
+        .last()
+        .expect("chain always has a genesis block");
+    TransactionReceipt {
+        transaction_id: block.transactions.first().map(|tx| tx.id()).unwrap_or_default(),
+        block_index: block.index,
+    }
+}
+
 async fn health() -> impl IntoResponse {
     StatusCode::OK
 }
@@ -68,15 +183,288 @@ async fn get_series_by_id(
     }
 }
 
+const DEFAULT_BLOCK_PAGE: usize = 20;
+const MAX_BLOCK_PAGE: usize = 100;
+
+/// Blocks in index order, `limit` at a time starting at `from`.
+async fn get_blocks(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<BlockPageQuery>,
+) -> impl IntoResponse {
+    let from = query.from.unwrap_or(0);
+    let limit = query.limit.unwrap_or(DEFAULT_BLOCK_PAGE);
+    if limit == 0 || limit > MAX_BLOCK_PAGE {
+        let message = format!("limit must be between 1 and {}", MAX_BLOCK_PAGE);
+        return json_error(StatusCode::UNPROCESSABLE_ENTITY, &message).into_response();
+    }
+
+    let deckchain = state.deckchain.read().await;
+    let blockchain = &deckchain.blockchain;
+    let blocks = blockchain.get_blocks_from(from, limit);
+    let total = blockchain.get_blocks().len() as u64;
+    let end = from + blocks.len() as u64;
+    Json(BlockPage {
+        blocks,
+        from,
+        limit,
+        total,
+        next: (!blocks.is_empty() && end < total).then_some(end),
+    })
+    .into_response()
+}
+
+async fn get_block_by_index(
+    State(state): State<Arc<AppState>>,
+    Path(index): Path<u64>,
+) -> impl IntoResponse {
+    let deckchain = state.deckchain.read().await;
+    match deckchain.blockchain.get_block(index) {
+        Some(block) => Json(block).into_response(),
+        None => {
+            let error = DeckForgeError::BlockNotFound { index };
+            json_error(error_status(&error), &error.to_string()).into_response()
+        }
+    }
+}
+
+async fn get_block_by_hash(
+    State(state): State<Arc<AppState>>,
+    Path(hash): Path<String>,
+) -> impl IntoResponse {
+    let deckchain = state.deckchain.read().await;
+    match deckchain.blockchain.get_block_by_hash(&hash) {
+        Some(block) => Json(block).into_response(),
+        None => {
+            let error = DeckForgeError::BlockHashNotFound { hash };
+            json_error(error_status(&error), &error.to_string()).into_response()
+        }
+    }
+}
+
+async fn get_latest_block(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let deckchain = state.deckchain.read().await;
+    let block = deckchain
+        .blockchain
+        .latest_block()
+        .expect("chain always has a genesis block");
+    Json(block).into_response()
+}
+
+async fn get_wallet_cards(
+    State(state): State<Arc<AppState>>,
+    Path(address): Path<String>,
+) -> impl IntoResponse {
+    let deckchain = state.deckchain.read().await;
+    match deckchain.wallet_cards(&address) {
+        Ok(cards) => Json(WalletCards {
+            address,
+            count: cards.len(),
+            cards,
+        })
+        .into_response(),
+        Err(e) => json_error(error_status(&e), &e.to_string()).into_response(),
+    }
+}
+
+async fn get_card(
+    State(state): State<Arc<AppState>>,
+    Path(card_id): Path<String>,
+) -> impl IntoResponse {
+    let deckchain = state.deckchain.read().await;
+    match deckchain.card(&card_id) {
+        Ok(card) => Json(card).into_response(),
+        Err(e) => json_error(error_status(&e), &e.to_string()).into_response(),
+    }
+}
+
+async fn get_card_history(
+    State(state): State<Arc<AppState>>,
+    Path(card_id): Path<String>,
+) -> impl IntoResponse {
+    let deckchain = state.deckchain.read().await;
+    match deckchain.card_history(&card_id) {
+        Ok(history) => Json(history).into_response(),
+        Err(e) => json_error(error_status(&e), &e.to_string()).into_response(),
+    }
+}
+
+/// A confirmed transaction with its block index, position and confirmations.
+async fn get_transaction(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let deckchain = state.deckchain.read().await;
+    match deckchain.transaction(&id) {
+        Ok(record) => Json(record).into_response(),
+        Err(e) => json_error(error_status(&e), &e.to_string()).into_response(),
+    }
+}
+
+/// Merkle inclusion proof of the `n`th transaction of a block, verifiable
+/// against the block hash alone.
+async fn get_transaction_proof(
+    State(state): State<Arc<AppState>>,
+    Path((index, position)): Path<(u64, usize)>,
+) -> impl IntoResponse {
+    let deckchain = state.deckchain.read().await;
+    let error = match deckchain.blockchain.get_block(index) {
+        None => DeckForgeError::BlockNotFound { index },
+        Some(block) if position >= block.transactions.len() => {
+            DeckForgeError::TransactionNotFound { index, position }
+        }
+        Some(block) => match block.transaction_proof(position) {
+            Some(proof) => return Json(proof).into_response(),
+            None => DeckForgeError::NoMerkleRoot { index },
+        },
+    };
+    json_error(error_status(&error), &error.to_string()).into_response()
+}
+
+async fn get_series_state(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let deckchain = state.deckchain.read().await;
+    match deckchain.series_state(&id) {
+        Ok(series_state) => Json(series_state).into_response(),
+        Err(e) => json_error(error_status(&e), &e.to_string()).into_response(),
+    }
+}
+
+async fn get_series_cards(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(query): Query<SeriesCardsQuery>,
+) -> impl IntoResponse {
+    let deckchain = state.deckchain.read().await;
+    match deckchain.series_cards(&id, query.rarity, query.card_type.as_deref()) {
+        Ok(cards) => Json(cards).into_response(),
+        Err(e) => json_error(error_status(&e), &e.to_string()).into_response(),
+    }
+}
+
+/// Releases the series in the request body, whose id hashes the body
+/// exactly as submitted, as a release from a file does.
+async fn post_series(
+    State(state): State<Arc<AppState>>,
+    series_data: String,
+) -> impl IntoResponse {
+    let mut deckchain = state.deckchain.write().await;
+    match deckchain.release_series_data(&series_data) {
+        Ok(()) => (StatusCode::CREATED, Json(last_receipt(&deckchain))).into_response(),
+        Err(e) => write_error(e),
+    }
+}
+
+async fn post_transfer(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<TransferRequest>,
+) -> impl IntoResponse {
+    let transaction = BlockTransaction::new(TransactionType::TransferCard {
+        card_id: request.card_id,
+        sender: request.sender,
+        receiver: request.receiver,
+        nonce: request.nonce,
+        signature: request.signature,
+    });
+
+    let mut deckchain = state.deckchain.write().await;
+    match deckchain.do_transfer(transaction) {
+        Ok(()) => (StatusCode::CREATED, Json(last_receipt(&deckchain))).into_response(),
+        Err(e) => write_error(e),
+    }
+}
+
+/// Publishes the private shuffle salt of a series released by this server.
+async fn post_reveal_shuffle(
+    State(state): State<Arc<AppState>>,
+    Path(series_id): Path<String>,
+) -> impl IntoResponse {
+    let mut deckchain = state.deckchain.write().await;
+    match deckchain.do_reveal_shuffle(&series_id) {
+        Ok(()) => (StatusCode::CREATED, Json(last_receipt(&deckchain))).into_response(),
+        Err(e) => write_error(e),
+    }
+}
+
+async fn post_open_pack(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<OpenPackRequest>,
+) -> impl IntoResponse {
+    let mut deckchain = state.deckchain.write().await;
+    match deckchain.do_open_pack(&request.series_id, &request.pack_id, &request.receiver) {
+        Ok(cards) => {
+            let receipt = last_receipt(&deckchain);
+            let response = OpenPackReceipt {
+                transaction_id: receipt.transaction_id,
+                block_index: receipt.block_index,
+                cards,
+            };
+            (StatusCode::CREATED, Json(response)).into_response()
+        }
+        Err(e) => write_error(e),
+    }
+}
+
+/// The authorized keys and their scopes. Public keys only, so nothing here
+/// lets a caller sign as another key.
+async fn get_keys(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(state.authorized_keys.current().keys.clone())
+}
+
 pub fn build_app(state: Arc<AppState>) -> Router {
-    let protected = Router::new()
+    let scoped = |scope: Scope| axum_middleware::from_fn_with_state(scope, require_scope);
+
+    let read = Router::new()
         .route("/blockchain", get(get_blockchain))
-        .route("/series", get(get_series_list))
-        .route("/series/{id}", get(get_series_by_id))
+        .route("/series/:id", get(get_series_by_id))
+        .route("/series/:id/state", get(get_series_state))
+        .route("/series/:id/cards", get(get_series_cards))
+        .route("/blocks", get(get_blocks))
+        .route("/blocks/latest", get(get_latest_block))
+        .route("/blocks/hash/:hash", get(get_block_by_hash))
+        .route("/blocks/:index", get(get_block_by_index))
+        .route("/wallets/:address/cards", get(get_wallet_cards))
+        .route("/cards/:card_id", get(get_card))
+        .route("/cards/:card_id/history", get(get_card_history))
+        .route("/tx/:id", get(get_transaction))
+        .route("/blocks/:index/tx/:n/proof", get(get_transaction_proof))
+        .route_layer(scoped(Scope::Read));
+
+    let transfer = Router::new()
+        .route("/transfers", post(post_transfer))
+        .route("/packs", post(post_open_pack))
+        .route_layer(scoped(Scope::Transfer));
+
+    let release = Router::new()
+        .route("/series/:id/reveal", post(post_reveal_shuffle))
+        .route_layer(scoped(Scope::Release));
+
+    let key_admin = Router::new()
+        .route("/keys", get(get_keys))
+        .route_layer(scoped(Scope::KeyAdmin));
+
+    // Readers list series; only release keys may add one.
+    let series = Router::new().route(
+        "/series",
+        get(get_series_list)
+            .route_layer(scoped(Scope::Read))
+            .post(post_series.layer(scoped(Scope::Release))),
+    );
+
+    let protected = read
+        .merge(transfer)
+        .merge(release)
+        .merge(key_admin)
+        .merge(series)
         .route_layer(axum_middleware::from_fn_with_state(state.clone(), require_auth));
 
+    // Browser `EventSource` and `WebSocket` clients cannot send signature
+    // headers, so the event streams are served unsigned alongside `/health`.
     let public = Router::new()
-        .route("/health", get(health));
+        .route("/health", get(health))
+        .route("/events", get(sse_events))
+        .route("/ws", get(ws_events));
 
     public.merge(protected).with_state(state)
 }
@@ -84,15 +472,22 @@ pub fn build_app(state: Arc<AppState>) -> Router {
 pub async fn start_server(config: Config) -> crate::error::Result<()> {
     let listen_addr = config.listen_addr().to_string();
     let deckchain = DeckChain::new(&config)?;
-    let authorized_keys = AuthorizedKeys::load_from_file(config.authorized_keys_path())
-        .unwrap_or_else(|_| {
-            tracing::warn!("No authorized_keys file found, starting with empty key set");
-            AuthorizedKeys::new()
-        });
+    let authorized_keys = WatchedKeys::load(config.authorized_keys_path());
 
     let state = Arc::new(AppState {
         deckchain: RwLock::new(deckchain),
         authorized_keys,
+        nonces: NonceCache::new(),
+    });
+
+    // Pick up keys added or revoked with `generate-key` without a restart.
+    let reload_state = state.clone();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(RELOAD_INTERVAL);
+        loop {
+            ticker.tick().await;
+            reload_state.authorized_keys.reload_if_changed();
+        }
     });
 
     let app = build_app(state);
@@ -109,18 +504,51 @@ pub mod tests {
     use super::*;
 
     use chrono::{Duration, Utc};
+    use serde_json::Value;
     use tempfile::TempDir;
     use tokio::task;
 
+    use crate::auth::keys::AuthorizedKeys;
+    use crate::auth::signing::SignedHeaders;
+    use crate::blockchain::block::TransactionProof;
+    use crate::crypto::keypair::KeyPair;
+
+    fn test_user_key() -> KeyPair {
+        KeyPair::from_secret_key(&"01".repeat(32)).unwrap()
+    }
+
+    fn test_admin_key() -> KeyPair {
+        KeyPair::from_secret_key(&"02".repeat(32)).unwrap()
+    }
+
+    fn test_reader_key() -> KeyPair {
+        KeyPair::from_secret_key(&"03".repeat(32)).unwrap()
+    }
+
+    fn sign_request(
+        request: reqwest::RequestBuilder,
+        key: &KeyPair,
+        method: &str,
+        endpoint: &str,
+        body: &[u8],
+    ) -> reqwest::RequestBuilder {
+        SignedHeaders::sign(key, method, endpoint, body)
+            .to_pairs()
+            .into_iter()
+            .fold(request, |request, (name, value)| request.header(name, value))
+    }
+
     fn init_test_state() -> (Arc<AppState>, TempDir) {
         let tmp_dir = TempDir::new().unwrap();
         let data_dir_path = format!("{}/data", tmp_dir.path().to_str().unwrap());
         std::fs::create_dir(&data_dir_path).unwrap();
 
+        let keys_path = format!("{}/authorized_keys.json", tmp_dir.path().to_str().unwrap());
         let config = Config {
             data_dir: data_dir_path,
             listen_addr: Some("127.0.0.1:0".to_string()),
-            authorized_keys_path: None,
+            authorized_keys_path: Some(keys_path.clone()),
+            storage: None,
         };
 
         let deckchain = DeckChain::new(&config).unwrap();
@@ -128,20 +556,40 @@ pub mod tests {
         let mut authorized_keys = AuthorizedKeys::new();
         authorized_keys.add_key(
             "test".to_string(),
-            "test-api-key".to_string(),
+            test_user_key().public_key_as_string(),
+            Utc::now() + Duration::hours(1),
+            Scope::DEFAULT.to_vec(),
+        );
+        authorized_keys.add_key(
+            "admin".to_string(),
+            test_admin_key().public_key_as_string(),
+            Utc::now() + Duration::hours(1),
+            Scope::ALL.to_vec(),
+        );
+        authorized_keys.add_key(
+            "reader".to_string(),
+            test_reader_key().public_key_as_string(),
             Utc::now() + Duration::hours(1),
+            vec![Scope::Read],
         );
+        authorized_keys.save_to_file(&keys_path).unwrap();
 
         let state = Arc::new(AppState {
             deckchain: RwLock::new(deckchain),
-            authorized_keys,
+            authorized_keys: WatchedKeys::load(&keys_path),
+            nonces: NonceCache::new(),
         });
 
         (state, tmp_dir)
     }
 
     async fn spawn_test_server() -> String {
-        let (state, _tmp_dir) = init_test_state();
+        let (state, tmp_dir) = init_test_state();
+        std::mem::forget(tmp_dir);
+        serve_test_state(state).await
+    }
+
+    async fn serve_test_state(state: Arc<AppState>) -> String {
         let app = build_app(state);
 
         let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
@@ -152,8 +600,6 @@ pub mod tests {
             axum::serve(listener, app).await.unwrap();
         });
 
-        std::mem::forget(_tmp_dir);
-
         wait_for_server_up(&base_url).await;
         base_url
     }
@@ -175,9 +621,8 @@ pub mod tests {
 
     async fn send_test_get_request(base_url: &str, endpoint: &str) -> (String, u16) {
         let client = reqwest::Client::new();
-        let resp = client
-            .get(format!("{}{}", base_url, endpoint))
-            .header("X-API-Key", "test-api-key")
+        let request = client.get(format!("{}{}", base_url, endpoint));
+        let resp = sign_request(request, &test_user_key(), "GET", endpoint, b"")
             .send()
             .await
             .unwrap();
@@ -222,15 +667,79 @@ pub mod tests {
     async fn test_get_blockchain_forbidden() {
         let base_url = spawn_test_server().await;
         let client = reqwest::Client::new();
-        let resp = client
-            .get(format!("{}/blockchain", base_url))
-            .header("X-API-Key", "wrong-key")
+        let request = client.get(format!("{}/blockchain", base_url));
+        let resp = sign_request(request, &KeyPair::new(), "GET", "/blockchain", b"")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 403);
+
+        // An unknown key is turned away before its body is read.
+        let request = client
+            .post(format!("{}/series", base_url))
+            .body(vec![b' '; crate::api::middleware::MAX_SIGNED_BODY + 1]);
+        let resp = sign_request(request, &KeyPair::new(), "POST", "/series", b"")
             .send()
             .await
             .unwrap();
         assert_eq!(resp.status(), 403);
     }
 
+    #[tokio::test]
+    async fn test_signed_request_replay_and_tampering() {
+        let base_url = spawn_test_server().await;
+        let client = reqwest::Client::new();
+        let send = |signed: &SignedHeaders, body: &'static [u8]| {
+            let request = client
+                .post(format!("{}/transfers", base_url))
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body);
+            signed
+                .to_pairs()
+                .into_iter()
+                .fold(request, |request, (name, value)| request.header(name, value))
+                .send()
+        };
+
+        let now = Utc::now().timestamp();
+        let signed = SignedHeaders::sign_at(&test_user_key(), "POST", "/transfers", b"{}", now, "n1".to_string());
+        let resp = send(&signed, b"{}").await.unwrap();
+        assert_eq!(resp.status(), 422);
+        let resp = send(&signed, b"{}").await.unwrap();
+        assert_eq!(resp.status(), 401);
+
+        let signed = SignedHeaders::sign_at(&test_user_key(), "POST", "/transfers", b"{}", now, "n2".to_string());
+        let resp = send(&signed, b"[]").await.unwrap();
+        assert_eq!(resp.status(), 401);
+
+        let stale = now - crate::auth::signing::REPLAY_WINDOW_SECS - 1;
+        let signed = SignedHeaders::sign_at(&test_user_key(), "POST", "/transfers", b"{}", stale, "n3".to_string());
+        let resp = send(&signed, b"{}").await.unwrap();
+        assert_eq!(resp.status(), 401);
+
+        let signed = SignedHeaders::sign_at(&test_user_key(), "POST", "/transfers", b"{}", i64::MIN, "n4".to_string());
+        let resp = send(&signed, b"{}").await.unwrap();
+        assert_eq!(resp.status(), 401);
+    }
+
+    #[tokio::test]
+    async fn test_reloaded_keys_apply_to_requests() {
+        let (state, tmp_dir) = init_test_state();
+        let base_url = serve_test_state(state.clone()).await;
+        let (_body, status) = send_test_get_request(&base_url, "/blockchain").await;
+        assert_eq!(status, 200);
+
+        let keys_path = format!("{}/authorized_keys.json", tmp_dir.path().to_str().unwrap());
+        let mut keys = AuthorizedKeys::load_from_file(&keys_path).unwrap();
+        keys.keys.retain(|key| key.label != "test");
+        keys.save_to_file(&keys_path).unwrap();
+        let changes = state.authorized_keys.reload_if_changed().unwrap();
+        assert!(changes.removed[0].starts_with("test ("));
+
+        let (_body, status) = send_test_get_request(&base_url, "/blockchain").await;
+        assert_eq!(status, 403);
+    }
+
     #[tokio::test]
     async fn test_get_nonexistent_route() {
         let base_url = spawn_test_server().await;
@@ -242,9 +751,8 @@ pub mod tests {
     async fn test_get_blockchain_method_not_allowed() {
         let base_url = spawn_test_server().await;
         let client = reqwest::Client::new();
-        let resp = client
-            .post(format!("{}/blockchain", base_url))
-            .header("X-API-Key", "test-api-key")
+        let request = client.post(format!("{}/blockchain", base_url));
+        let resp = sign_request(request, &test_user_key(), "POST", "/blockchain", b"")
             .send()
             .await
             .unwrap();
@@ -259,6 +767,247 @@ pub mod tests {
         assert_eq!(body, "[]");
     }
 
+    async fn send_test_post_request(
+        base_url: &str,
+        endpoint: &str,
+        key: &KeyPair,
+        body: &Value,
+    ) -> (Value, u16) {
+        let client = reqwest::Client::new();
+        let body = serde_json::to_vec(body).unwrap();
+        let request = client
+            .post(format!("{}{}", base_url, endpoint))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.clone());
+        let resp = sign_request(request, key, "POST", endpoint, &body)
+            .send()
+            .await
+            .unwrap();
+        let status = resp.status().as_u16();
+        let body = resp.json().await.unwrap_or(Value::Null);
+        (body, status)
+    }
+
+    #[tokio::test]
+    async fn test_post_series_release() {
+        let base_url = spawn_test_server().await;
+        let series = crate::card::series::tests::test_series_json();
+
+        let (body, status) = send_test_post_request(&base_url, "/series", &test_admin_key(), &series).await;
+        assert_eq!(status, 201, "body was: {}", body);
+        assert_eq!(body["block_index"], 1);
+        assert_eq!(body["transaction_id"].as_str().unwrap().len(), 64);
+
+        let (_body, status) = send_test_get_request(&base_url, "/series/LEGACYDECK-1").await;
+        assert_eq!(status, 200);
+
+        let (_body, status) = send_test_post_request(&base_url, "/series", &test_admin_key(), &series).await;
+        assert_eq!(status, 409);
+    }
+
+    #[tokio::test]
+    async fn test_post_reveal_shuffle() {
+        let base_url = spawn_test_server().await;
+        let series = crate::card::series::tests::test_series_json();
+        send_test_post_request(&base_url, "/series", &test_admin_key(), &series).await;
+
+        let endpoint = "/series/LEGACYDECK-1/reveal";
+        let (_body, status) = send_test_post_request(&base_url, endpoint, &test_user_key(), &Value::Null).await;
+        assert_eq!(status, 403);
+
+        let (body, status) = send_test_post_request(&base_url, endpoint, &test_admin_key(), &Value::Null).await;
+        assert_eq!(status, 201, "body was: {}", body);
+        assert_eq!(body["block_index"], 2);
+
+        let (_body, status) = send_test_post_request(&base_url, endpoint, &test_admin_key(), &Value::Null).await;
+        assert_eq!(status, 409);
+    }
+
+    #[tokio::test]
+    async fn test_post_series_hashes_submitted_body() {
+        use sha3::{Digest, Sha3_256};
+
+        let base_url = spawn_test_server().await;
+        let series = crate::card::series::tests::test_series_json();
+        let body = serde_json::to_vec_pretty(&series).unwrap();
+        let request = reqwest::Client::new()
+            .post(format!("{}/series", base_url))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.clone());
+        let resp = sign_request(request, &test_admin_key(), "POST", "/series", &body)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 201);
+        let receipt: Value = resp.json().await.unwrap();
+
+        let endpoint = format!("/tx/{}", receipt["transaction_id"].as_str().unwrap());
+        let (record, status) = send_test_get_request(&base_url, &endpoint).await;
+        assert_eq!(status, 200);
+        let record: Value = serde_json::from_str(&record).unwrap();
+        assert_eq!(
+            record["transaction"]["transaction_type"]["ReleaseSet"]["id"],
+            hex::encode(Sha3_256::digest(&body))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_post_series_requires_release_scope() {
+        let base_url = spawn_test_server().await;
+        let series = crate::card::series::tests::test_series_json();
+        let (body, status) =
+            send_test_post_request(&base_url, "/series", &test_user_key(), &series).await;
+        assert_eq!(status, 403);
+        assert_eq!(body["error"], "Key lacks the 'release' scope");
+
+        let (_body, status) = send_test_get_request(&base_url, "/series").await;
+        assert_eq!(status, 200);
+    }
+
+    #[tokio::test]
+    async fn test_read_only_key() {
+        let base_url = spawn_test_server().await;
+        let client = reqwest::Client::new();
+        let get = |endpoint: &'static str, key: KeyPair| {
+            let request = client.get(format!("{}{}", base_url, endpoint));
+            sign_request(request, &key, "GET", endpoint, b"").send()
+        };
+
+        let resp = get("/blockchain", test_reader_key()).await.unwrap();
+        assert_eq!(resp.status(), 200);
+        let resp = get("/series", test_reader_key()).await.unwrap();
+        assert_eq!(resp.status(), 200);
+
+        let pack = serde_json::json!({"series_id": "LEGACYDECK-1", "pack_id": "booster", "receiver": "0xabc"});
+        let (body, status) =
+            send_test_post_request(&base_url, "/packs", &test_reader_key(), &pack).await;
+        assert_eq!(status, 403);
+        assert_eq!(body["error"], "Key lacks the 'transfer' scope");
+
+        let resp = get("/keys", test_reader_key()).await.unwrap();
+        assert_eq!(resp.status(), 403);
+        let resp = get("/keys", test_admin_key()).await.unwrap();
+        assert_eq!(resp.status(), 200);
+        let keys: Value = resp.json().await.unwrap();
+        assert_eq!(keys[2]["label"], "reader");
+        assert_eq!(keys[2]["scopes"], serde_json::json!(["read"]));
+    }
+
+    #[tokio::test]
+    async fn test_post_series_invalid() {
+        let base_url = spawn_test_server().await;
+        let mut series = crate::card::series::tests::test_series_json();
+        series["config"]["distribution"]["mint"]["total"] = Value::from(0);
+        let (body, status) =
+            send_test_post_request(&base_url, "/series", &test_admin_key(), &series).await;
+        assert_eq!(status, 422, "body was: {}", body);
+
+        let (body, _status) = send_test_get_request(&base_url, "/series").await;
+        assert_eq!(body, "[]");
+    }
+
+    #[tokio::test]
+    async fn test_post_pack_and_transfer() {
+        let base_url = spawn_test_server().await;
+        let series = crate::card::series::tests::test_series_json();
+        send_test_post_request(&base_url, "/series", &test_admin_key(), &series).await;
+
+        let key_pair = crate::crypto::keypair::KeyPair::new();
+        let owner = crate::crypto::wallet::Wallet::pub_key_to_wallet_address(
+            &key_pair.public_key_as_string(),
+        )
+        .unwrap();
+        let request = serde_json::json!({
+            "series_id": "LEGACYDECK-1",
+            "pack_id": "booster",
+            "receiver": owner,
+        });
+        let (body, status) = send_test_post_request(&base_url, "/packs", &test_user_key(), &request).await;
+        assert_eq!(status, 201, "body was: {}", body);
+        assert_eq!(body["block_index"], 2);
+        assert_eq!(body["cards"].as_array().unwrap().len(), 10);
+
+        let card = &body["cards"][0];
+        let card_id = format!(
+            "{}-{}-{}",
+            card["series"].as_str().unwrap(),
+            card["number"],
+            card["serial"].as_str().unwrap()
+        );
+        let friend = "0x8ba82d54332db0c58edc1120a15409aa8cd5f7d9";
+        let transfer = BlockTransaction::new_transfer(&key_pair, card_id, friend.to_string(), 0)
+        .unwrap();
+        let request = match &transfer.transaction_type {
+            TransactionType::TransferCard { card_id, sender, receiver, nonce, signature } => {
+                serde_json::json!({
+                    "card_id": card_id,
+                    "sender": sender,
+                    "receiver": receiver,
+                    "nonce": nonce,
+                    "signature": signature,
+                })
+            }
+            _ => unreachable!(),
+        };
+        let (body, status) = send_test_post_request(&base_url, "/transfers", &test_user_key(), &request).await;
+        assert_eq!(status, 201, "body was: {}", body);
+        assert_eq!(body["transaction_id"], transfer.id());
+        assert_eq!(body["block_index"], 3);
+
+        let (_body, status) = send_test_post_request(&base_url, "/transfers", &test_user_key(), &request).await;
+        assert_eq!(status, 409);
+
+        let (body, status) = send_test_get_request(&base_url, &format!("/tx/{}", transfer.id())).await;
+        assert_eq!(status, 200, "body was: {}", body);
+        let record: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(record["block_index"], 3);
+        assert_eq!(record["position"], 0);
+        assert_eq!(record["confirmations"], 1);
+        assert_eq!(record["transaction"]["transaction_type"]["TransferCard"]["nonce"], 0);
+
+        let (_body, status) = send_test_get_request(&base_url, "/tx/unknown").await;
+        assert_eq!(status, 404);
+
+        let transferred = transfer.id();
+        let (body, status) =
+            send_test_get_request(&base_url, &format!("/cards/{}/history", request["card_id"].as_str().unwrap())).await;
+        assert_eq!(status, 200, "body was: {}", body);
+        let history: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(history.as_array().unwrap().len(), 3);
+        assert_eq!(history[2]["transaction_id"], transferred);
+        assert_eq!(history[2]["from"], owner);
+
+        let (body, status) =
+            send_test_get_request(&base_url, &format!("/cards/{}", request["card_id"].as_str().unwrap())).await;
+        assert_eq!(status, 200, "body was: {}", body);
+        let card: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(card["series"], "LEGACYDECK-1");
+        assert_eq!(card["owner"], friend);
+        assert_eq!(card["transfers"], 1);
+        assert!(card["title"].is_string());
+
+        let (body, status) = send_test_get_request(&base_url, &format!("/wallets/{}/cards", owner)).await;
+        assert_eq!(status, 200);
+        let wallet: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(wallet["count"], 9);
+
+        let (_body, status) = send_test_get_request(&base_url, "/cards/LEGACYDECK-1-9999-001").await;
+        assert_eq!(status, 404);
+    }
+
+    #[tokio::test]
+    async fn test_post_transfer_unauthorized() {
+        let base_url = spawn_test_server().await;
+        let client = reqwest::Client::new();
+        let resp = client
+            .post(format!("{}/transfers", base_url))
+            .json(&serde_json::json!({}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 401);
+    }
+
     #[tokio::test]
     async fn test_get_series_not_found() {
         let base_url = spawn_test_server().await;
@@ -266,4 +1015,150 @@ pub mod tests {
         assert_eq!(status, 404);
         assert_eq!(status, 404, "body was: {}", body);
     }
+
+    #[tokio::test]
+    async fn test_get_transaction_proof() {
+        let base_url = spawn_test_server().await;
+        let (body, status) = send_test_get_request(&base_url, "/blocks/0/tx/0/proof").await;
+        assert_eq!(status, 200, "body was: {}", body);
+        let proof: TransactionProof = serde_json::from_str(&body).unwrap();
+        assert_eq!(proof.block_index, 0);
+        assert!(proof.verify());
+
+        let (_, status) = send_test_get_request(&base_url, "/blocks/0/tx/1/proof").await;
+        assert_eq!(status, 404);
+        let (_, status) = send_test_get_request(&base_url, "/blocks/99/tx/0/proof").await;
+        assert_eq!(status, 404);
+    }
+
+    #[tokio::test]
+    async fn test_series_state_and_cards() {
+        let base_url = spawn_test_server().await;
+        let series = crate::card::series::tests::test_series_json();
+        send_test_post_request(&base_url, "/series", &test_admin_key(), &series).await;
+
+        let (body, status) = send_test_get_request(&base_url, "/series/LEGACYDECK-1/state").await;
+        assert_eq!(status, 200, "body was: {}", body);
+        let state: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(state["total_minted"], state["remaining"]);
+        assert_eq!(state["packs_opened"], 0);
+        assert!(state["shuffle"]["shuffle_hash"].is_string());
+        assert_eq!(state["shuffle"]["revealed"], false);
+
+        let (body, status) = send_test_get_request(&base_url, "/series/LEGACYDECK-1/cards?rarity=1").await;
+        assert_eq!(status, 200, "body was: {}", body);
+        let cards: Vec<Value> = serde_json::from_str(&body).unwrap();
+        assert!(!cards.is_empty());
+        assert!(cards.iter().all(|card| card["rarity"] == 1));
+
+        let (_, status) = send_test_get_request(&base_url, "/series/MISSING/state").await;
+        assert_eq!(status, 404);
+        let (_, status) = send_test_get_request(&base_url, "/series/MISSING/cards").await;
+        assert_eq!(status, 404);
+    }
+
+    #[tokio::test]
+    async fn test_block_explorer() {
+        let base_url = spawn_test_server().await;
+        let series = crate::card::series::tests::test_series_json();
+        send_test_post_request(&base_url, "/series", &test_admin_key(), &series).await;
+
+        let (body, status) = send_test_get_request(&base_url, "/blocks?limit=1").await;
+        assert_eq!(status, 200, "body was: {}", body);
+        let page: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(page["total"], 2);
+        assert_eq!(page["blocks"].as_array().unwrap().len(), 1);
+        assert_eq!(page["next"], 1);
+
+        let (body, _) = send_test_get_request(&base_url, "/blocks?from=1&limit=1").await;
+        let page: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(page["blocks"][0]["index"], 1);
+        assert!(page["next"].is_null());
+
+        let (body, status) = send_test_get_request(&base_url, "/blocks/latest").await;
+        assert_eq!(status, 200);
+        let latest: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(latest["index"], 1);
+
+        let hash = latest["hash"].as_str().unwrap();
+        let (body, status) = send_test_get_request(&base_url, &format!("/blocks/hash/{}", hash)).await;
+        assert_eq!(status, 200);
+        assert_eq!(serde_json::from_str::<Value>(&body).unwrap(), latest);
+
+        let (body, status) = send_test_get_request(&base_url, "/blocks/1").await;
+        assert_eq!(status, 200);
+        assert_eq!(serde_json::from_str::<Value>(&body).unwrap(), latest);
+
+        let (_, status) = send_test_get_request(&base_url, "/blocks/2").await;
+        assert_eq!(status, 404);
+        let (_, status) = send_test_get_request(&base_url, "/blocks/hash/missing").await;
+        assert_eq!(status, 404);
+        let (_, status) = send_test_get_request(&base_url, "/blocks?limit=0").await;
+        assert_eq!(status, 422);
+    }
+
+    #[tokio::test]
+    async fn test_sse_events() {
+        let base_url = spawn_test_server().await;
+        let client = reqwest::Client::new();
+        let endpoint = "/events?from_block=0&series=LEGACYDECK-1";
+        let mut resp = client.get(format!("{}{}", base_url, endpoint)).send().await.unwrap();
+        assert_eq!(resp.status(), 200);
+
+        let series = crate::card::series::tests::test_series_json();
+        send_test_post_request(&base_url, "/series", &test_admin_key(), &series).await;
+
+        let mut received = String::new();
+        while !received.contains("id: 1\n") {
+            let chunk = tokio::time::timeout(std::time::Duration::from_secs(5), resp.chunk())
+                .await
+                .expect("timed out waiting for events")
+                .unwrap()
+                .unwrap();
+            received.push_str(&String::from_utf8_lossy(&chunk));
+        }
+        let genesis = received.find("event: BlockAdded").unwrap();
+        let released = received.find("event: SeriesReleased").unwrap();
+        assert!(genesis < released);
+        assert!(received.contains("event: CardMinted"));
+        assert!(received.contains("id: 0\n"));
+    }
+
+    #[tokio::test]
+    async fn test_ws_events() {
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+        use tokio_tungstenite::tungstenite::Message;
+        use tokio_stream::StreamExt;
+
+        let base_url = spawn_test_server().await;
+        let series = crate::card::series::tests::test_series_json();
+        send_test_post_request(&base_url, "/series", &test_admin_key(), &series).await;
+
+        let endpoint = "/ws?from_block=1&address=0x8ba82d54332db0c58edc1120a15409aa8cd5f7d9";
+        let url = format!("{}{}", base_url.replace("http", "ws"), endpoint);
+        let (mut socket, _) = tokio_tungstenite::connect_async(url.into_client_request().unwrap()).await.unwrap();
+
+        let open_pack = serde_json::json!({
+            "series_id": "LEGACYDECK-1",
+            "pack_id": "booster",
+            "receiver": "0x8ba82d54332db0c58edc1120a15409aa8cd5f7d9",
+        });
+        send_test_post_request(&base_url, "/packs", &test_user_key(), &open_pack).await;
+
+        let mut events = Vec::new();
+        while events.last().map(|e: &Value| e["block_index"] != 2 || e["type"] != "BlockAdded").unwrap_or(true) {
+            let message = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next())
+                .await
+                .expect("timed out waiting for events")
+                .unwrap()
+                .unwrap();
+            if let Message::Text(text) = message {
+                events.push(serde_json::from_str(&text).unwrap());
+            }
+        }
+        let types: Vec<&str> = events.iter().map(|e| e["type"].as_str().unwrap()).collect();
+        assert_eq!(types[0], "BlockAdded");
+        assert_eq!(types.iter().filter(|t| **t == "CardTransferred").count(), 10);
+        assert!(!types.contains(&"CardMinted"));
+    }
 }
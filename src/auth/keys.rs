@@ -1,19 +1,124 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::fs;
+use sha3::{Digest, Sha3_256};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Write;
 use std::path::Path;
+use std::str::FromStr;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+use crate::blockchain::storage::sync_parent_dir;
+use crate::error::DeckForgeError;
+
+/// What an authorized key may do. Routes name the scope they require.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scope {
+    /// Read the chain, series, cards and event streams.
+    Read,
+    /// Open packs and transfer cards.
+    Transfer,
+    /// Release new series.
+    Release,
+    /// Inspect the authorized keys.
+    KeyAdmin,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 4] = [Scope::Read, Scope::Transfer, Scope::Release, Scope::KeyAdmin];
+
+    /// What keys without explicit scopes could do before scopes existed.
+    pub const DEFAULT: [Scope; 2] = [Scope::Read, Scope::Transfer];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Transfer => "transfer",
+            Scope::Release => "release",
+            Scope::KeyAdmin => "key-admin",
+        }
+    }
+}
+
+impl FromStr for Scope {
+    type Err = DeckForgeError;
+
+    fn from_str(name: &str) -> crate::error::Result<Self> {
+        Scope::ALL
+            .into_iter()
+            .find(|scope| scope.name() == name)
+            .ok_or_else(|| DeckForgeError::UnknownScope {
+                name: name.to_string(),
+            })
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "StoredKey")]
 pub struct AuthorizedKey {
     pub label: String,
     pub public_key: String,
     pub expiry: DateTime<Utc>,
+    pub scopes: Vec<Scope>,
+}
+
+/// An `authorized_keys.json` entry as written by any version. Files from
+/// before scopes carry an `admin` flag instead, which maps to every scope;
+/// the file is rewritten with scopes the next time it is saved.
+#[derive(Deserialize)]
+struct StoredKey {
+    label: String,
+    public_key: String,
+    expiry: DateTime<Utc>,
+    #[serde(default)]
+    admin: bool,
+    scopes: Option<Vec<Scope>>,
+}
+
+impl From<StoredKey> for AuthorizedKey {
+    fn from(stored: StoredKey) -> Self {
+        let scopes = stored.scopes.unwrap_or_else(|| {
+            if stored.admin {
+                Scope::ALL.to_vec()
+            } else {
+                Scope::DEFAULT.to_vec()
+            }
+        });
+        AuthorizedKey {
+            label: stored.label,
+            public_key: stored.public_key,
+            expiry: stored.expiry,
+            scopes,
+        }
+    }
 }
 
 impl AuthorizedKey {
     pub fn is_expired(&self) -> bool {
         Utc::now() > self.expiry
     }
+
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope)
+    }
+
+    /// A short, stable identifier for the public key: the first 8 bytes of
+    /// its Sha3-256 digest, in hex.
+    pub fn fingerprint(&self) -> String {
+        let bytes = hex::decode(&self.public_key).unwrap_or_else(|_| self.public_key.as_bytes().to_vec());
+        hex::encode(&Sha3_256::digest(bytes)[..8])
+    }
+
+    /// `label (fingerprint)`, for logs.
+    pub fn describe(&self) -> String {
+        format!("{} ({})", self.label, self.fingerprint())
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -26,22 +131,29 @@ impl AuthorizedKeys {
         AuthorizedKeys { keys: Vec::new() }
     }
 
-    pub fn add_key(&mut self, label: String, public_key: String, expiry: DateTime<Utc>) {
+    pub fn add_key(&mut self, label: String, public_key: String, expiry: DateTime<Utc>, scopes: Vec<Scope>) {
         let key = AuthorizedKey {
             label,
             public_key,
             expiry,
+            scopes,
         };
         self.keys.push(key);
     }
 
+    /// Writes the keys via a temporary file, so a server reloading the file
+    /// never reads it half-written.
     pub fn save_to_file(&self, file_path: &str) -> crate::error::Result<()> {
         let json = serde_json::to_string_pretty(&self)?;
         if let Some(parent) = Path::new(file_path).parent() {
             fs::create_dir_all(parent)?;
         }
-        fs::write(file_path, json)?;
-        Ok(())
+        let tmp_path = format!("{}.tmp", file_path);
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(json.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, file_path)?;
+        sync_parent_dir(file_path)
     }
 
     pub fn load_from_file(file_path: &str) -> crate::error::Result<Self> {
@@ -50,9 +162,120 @@ impl AuthorizedKeys {
         Ok(keys)
     }
 
-    pub fn is_key_authorized(&self, public_key: &str) -> bool {
+    /// Loads the file, or an empty key set when it does not exist yet. A file
+    /// that exists but does not parse is an error, so it is never overwritten.
+    pub fn load_or_new(file_path: &str) -> crate::error::Result<Self> {
+        if Path::new(file_path).exists() {
+            AuthorizedKeys::load_from_file(file_path)
+        } else {
+            Ok(AuthorizedKeys::new())
+        }
+    }
+
+    /// The index of the key named by its public key or, failing that, by
+    /// its label. A label shared by several keys is ambiguous.
+    fn position(&self, name: &str) -> crate::error::Result<usize> {
+        let public_key = name.to_ascii_lowercase();
+        if let Some(index) = self.keys.iter().position(|key| key.public_key == public_key) {
+            return Ok(index);
+        }
+        let labelled: Vec<usize> = (0..self.keys.len()).filter(|&i| self.keys[i].label == name).collect();
+        match labelled.as_slice() {
+            [index] => Ok(*index),
+            [] => Err(DeckForgeError::KeyNotFound { name: name.to_string() }),
+            _ => Err(DeckForgeError::AmbiguousKeyLabel {
+                label: name.to_string(),
+                count: labelled.len(),
+            }),
+        }
+    }
+
+    /// The key named by its public key or label, expired or not.
+    pub fn get_mut(&mut self, name: &str) -> crate::error::Result<&mut AuthorizedKey> {
+        let index = self.position(name)?;
+        Ok(&mut self.keys[index])
+    }
+
+    pub fn remove(&mut self, name: &str) -> crate::error::Result<AuthorizedKey> {
+        let index = self.position(name)?;
+        Ok(self.keys.remove(index))
+    }
+
+    /// Fails when a key already uses this label or public key, so keys stay
+    /// addressable by either.
+    pub fn ensure_unused(&self, label: &str, public_key: &str) -> crate::error::Result<()> {
+        for name in [label, public_key] {
+            if self.keys.iter().any(|key| key.label == name || key.public_key == name) {
+                return Err(DeckForgeError::DuplicateKey { name: name.to_string() });
+            }
+        }
+        Ok(())
+    }
+
+    /// The unexpired key with this public key, if any.
+    pub fn authorized_key(&self, public_key: &str) -> Option<&AuthorizedKey> {
         self.keys
             .iter()
-            .any(|key| key.public_key == public_key && !key.is_expired())
+            .find(|key| key.public_key == public_key && !key.is_expired())
+    }
+
+    #[allow(dead_code)] // public API
+    pub fn is_key_authorized(&self, public_key: &str) -> bool {
+        self.authorized_key(public_key).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_admin_flag_migrates_to_scopes() {
+        let json = r#"{"keys": [
+            {"label": "old-admin", "public_key": "aa", "expiry": "2999-01-01T00:00:00Z", "admin": true},
+            {"label": "old-user", "public_key": "bb", "expiry": "2999-01-01T00:00:00Z"},
+            {"label": "reader", "public_key": "cc", "expiry": "2999-01-01T00:00:00Z", "scopes": ["read"]}
+        ]}"#;
+        let keys: AuthorizedKeys = serde_json::from_str(json).unwrap();
+        assert_eq!(keys.keys[0].scopes, Scope::ALL);
+        assert_eq!(keys.keys[1].scopes, Scope::DEFAULT);
+        assert!(keys.authorized_key("cc").unwrap().has_scope(Scope::Read));
+        assert!(!keys.authorized_key("cc").unwrap().has_scope(Scope::Transfer));
+
+        let saved = serde_json::to_value(&keys).unwrap();
+        assert!(saved["keys"][0].get("admin").is_none());
+        assert_eq!(saved["keys"][0]["scopes"][3], "key-admin");
+    }
+
+    #[test]
+    fn test_keys_named_by_label_or_public_key() {
+        let expiry = Utc::now();
+        let mut keys = AuthorizedKeys::new();
+        keys.add_key("alice".to_string(), "aa".to_string(), expiry, vec![Scope::Read]);
+        keys.add_key("shared".to_string(), "bb".to_string(), expiry, vec![Scope::Read]);
+        keys.add_key("shared".to_string(), "cc".to_string(), expiry, vec![Scope::Read]);
+
+        assert_eq!(keys.get_mut("alice").unwrap().public_key, "aa");
+        assert_eq!(keys.get_mut("CC").unwrap().public_key, "cc");
+        assert!(matches!(
+            keys.get_mut("shared"),
+            Err(DeckForgeError::AmbiguousKeyLabel { count: 2, .. })
+        ));
+        assert!(matches!(keys.remove("bob"), Err(DeckForgeError::KeyNotFound { .. })));
+
+        assert!(keys.ensure_unused("alice", "dd").is_err());
+        assert!(keys.ensure_unused("dave", "bb").is_err());
+        assert!(keys.ensure_unused("dave", "dd").is_ok());
+
+        assert_eq!(keys.remove("bb").unwrap().public_key, "bb");
+        assert_eq!(keys.get_mut("shared").unwrap().public_key, "cc");
+    }
+
+    #[test]
+    fn test_scope_names_round_trip() {
+        for scope in Scope::ALL {
+            assert_eq!(scope.name().parse::<Scope>().unwrap(), scope);
+        }
+        assert!("admin".parse::<Scope>().is_err());
     }
 }
@@ -1 +1,3 @@
-pub mod keys;
\ No newline at end of file
+pub mod keys;
+pub mod signing;
+pub mod watch;
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use axum::http::HeaderMap;
+use chrono::Utc;
+use sha3::{Digest, Sha3_256};
+
+use crate::crypto::keypair::KeyPair;
+
+pub const KEY_HEADER: &str = "X-DeckForge-Key";
+pub const TIMESTAMP_HEADER: &str = "X-DeckForge-Timestamp";
+pub const NONCE_HEADER: &str = "X-DeckForge-Nonce";
+pub const SIGNATURE_HEADER: &str = "X-DeckForge-Signature";
+
+/// How far, in seconds, a request's timestamp may be from the server clock.
+pub const REPLAY_WINDOW_SECS: i64 = 300;
+const MAX_NONCE_LEN: usize = 128;
+
+/// The authentication headers of a signed API request.
+///
+/// The client signs the Sha3-256 digest of
+/// `deckforge-request-v1\n{METHOD}\n{path and query}\n{timestamp}\n{nonce}\n{hex Sha3-256 of body}`
+/// with its secret key, and sends its public key, the unix timestamp, the
+/// nonce and the compact hex signature as headers. The public key only names
+/// the key; the secret never leaves the client.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignedHeaders {
+    pub public_key: String,
+    pub timestamp: i64,
+    pub nonce: String,
+    pub signature: String,
+}
+
+impl SignedHeaders {
+    /// Signs a request now, with a fresh random nonce.
+    pub fn sign(key_pair: &KeyPair, method: &str, path: &str, body: &[u8]) -> Self {
+        let nonce = uuid::Uuid::new_v4().simple().to_string();
+        SignedHeaders::sign_at(key_pair, method, path, body, Utc::now().timestamp(), nonce)
+    }
+
+    pub fn sign_at(key_pair: &KeyPair, method: &str, path: &str, body: &[u8], timestamp: i64, nonce: String) -> Self {
+        let digest = SignedHeaders::request_digest(method, path, timestamp, &nonce, body);
+        SignedHeaders {
+            public_key: key_pair.public_key_as_string(),
+            timestamp,
+            nonce,
+            signature: key_pair.sign(digest),
+        }
+    }
+
+    pub fn request_digest(method: &str, path: &str, timestamp: i64, nonce: &str, body: &[u8]) -> [u8; 32] {
+        let payload = format!(
+            "deckforge-request-v1\n{}\n{}\n{}\n{}\n{}",
+            method.to_ascii_uppercase(),
+            path,
+            timestamp,
+            nonce,
+            hex::encode(Sha3_256::digest(body))
+        );
+        Sha3_256::digest(payload.as_bytes()).into()
+    }
+
+    /// Reads the headers, or `None` when any is missing or malformed.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let get = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        let nonce = get(NONCE_HEADER)?;
+        if nonce.is_empty() || nonce.len() > MAX_NONCE_LEN {
+            return None;
+        }
+        Some(SignedHeaders {
+            public_key: get(KEY_HEADER)?.to_ascii_lowercase(),
+            timestamp: get(TIMESTAMP_HEADER)?.parse().ok()?,
+            nonce: nonce.to_string(),
+            signature: get(SIGNATURE_HEADER)?.to_string(),
+        })
+    }
+
+    /// The headers as name/value pairs, for an HTTP client to send.
+    pub fn to_pairs(&self) -> [(&'static str, String); 4] {
+        [
+            (KEY_HEADER, self.public_key.clone()),
+            (TIMESTAMP_HEADER, self.timestamp.to_string()),
+            (NONCE_HEADER, self.nonce.clone()),
+            (SIGNATURE_HEADER, self.signature.clone()),
+        ]
+    }
+
+    pub fn verify(&self, method: &str, path: &str, body: &[u8]) -> bool {
+        let digest = SignedHeaders::request_digest(method, path, self.timestamp, &self.nonce, body);
+        KeyPair::verify(&self.public_key, digest, &self.signature)
+    }
+
+    /// Whether the timestamp is within the replay window of `now`. The
+    /// timestamp is unauthenticated input, so this must not overflow.
+    pub fn is_fresh(&self, now: i64) -> bool {
+        now.abs_diff(self.timestamp) <= REPLAY_WINDOW_SECS as u64
+    }
+}
+
+/// The nonces seen within the replay window, per key. Older requests are
+/// already refused by their timestamp, so their nonces are forgotten.
+#[derive(Default)]
+pub struct NonceCache {
+    seen: Mutex<HashMap<(String, String), i64>>,
+}
+
+impl NonceCache {
+    pub fn new() -> Self {
+        NonceCache::default()
+    }
+
+    /// Records a nonce, returning false when the key has already used it.
+    pub fn check_and_insert(&self, public_key: &str, nonce: &str, timestamp: i64, now: i64) -> bool {
+        let mut seen = self.seen.lock().expect("nonce cache lock poisoned");
+        seen.retain(|_, seen_at| now - *seen_at <= REPLAY_WINDOW_SECS);
+        let key = (public_key.to_string(), nonce.to_string());
+        if seen.contains_key(&key) {
+            return false;
+        }
+        // Keep the nonce for as long as its timestamp could still pass.
+        seen.insert(key, timestamp.max(now));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_request_verifies() {
+        let key_pair = KeyPair::new();
+        let signed = SignedHeaders::sign(&key_pair, "post", "/series?x=1", b"{}");
+        assert!(signed.verify("POST", "/series?x=1", b"{}"));
+        assert!(!signed.verify("POST", "/series?x=1", b"{ }"));
+        assert!(!signed.verify("POST", "/series", b"{}"));
+        assert!(!signed.verify("GET", "/series?x=1", b"{}"));
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in signed.to_pairs() {
+            headers.insert(name, value.parse().unwrap());
+        }
+        assert_eq!(SignedHeaders::from_headers(&headers), Some(signed.clone()));
+
+        let mut forged = signed;
+        forged.public_key = KeyPair::new().public_key_as_string();
+        assert!(!forged.verify("POST", "/series?x=1", b"{}"));
+    }
+
+    #[test]
+    fn test_replay_window_and_nonces() {
+        let key_pair = KeyPair::new();
+        let signed = SignedHeaders::sign_at(&key_pair, "GET", "/", b"", 1_000, "n".to_string());
+        assert!(signed.is_fresh(1_000 + REPLAY_WINDOW_SECS));
+        assert!(!signed.is_fresh(1_001 + REPLAY_WINDOW_SECS));
+        assert!(!signed.is_fresh(999 - REPLAY_WINDOW_SECS));
+        for extreme in [i64::MIN, i64::MAX] {
+            let signed = SignedHeaders::sign_at(&key_pair, "GET", "/", b"", extreme, "n".to_string());
+            assert!(!signed.is_fresh(1_000));
+            assert!(!signed.is_fresh(-1_000));
+        }
+
+        let nonces = NonceCache::new();
+        assert!(nonces.check_and_insert("key", "n", 1_000, 1_000));
+        assert!(!nonces.check_and_insert("key", "n", 1_000, 1_010));
+        assert!(nonces.check_and_insert("other", "n", 1_000, 1_010));
+        assert!(nonces.check_and_insert("key", "n", 2_000, 2_000));
+    }
+}
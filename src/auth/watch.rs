@@ -0,0 +1,203 @@
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+use crate::auth::keys::AuthorizedKeys;
+
+/// How often a running server checks its authorized keys file for changes.
+pub const RELOAD_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A file's inode, modification time and length, or `None` while it is
+/// missing. The inode catches a file replaced by rename within the same
+/// mtime tick and at the same length; where there are no inodes it is 0.
+type FileStamp = Option<(u64, SystemTime, u64)>;
+
+fn file_stamp(path: &str) -> FileStamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((file_inode(&metadata), metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(unix)]
+fn file_inode(metadata: &fs::Metadata) -> u64 {
+    metadata.ino()
+}
+
+#[cfg(not(unix))]
+fn file_inode(_metadata: &fs::Metadata) -> u64 {
+    0
+}
+
+/// The authorized keys of a running server, reloaded when their file
+/// changes. Requests take a snapshot with `current`, and a reload swaps in
+/// the whole new set at once, so no request sees a mix of old and new keys.
+pub struct WatchedKeys {
+    path: String,
+    keys: RwLock<Arc<AuthorizedKeys>>,
+    stamp: Mutex<FileStamp>,
+}
+
+/// How a reload changed the key set, as `label (fingerprint)` descriptions.
+#[derive(Debug, Default, PartialEq)]
+pub struct KeyChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub updated: Vec<String>,
+}
+
+impl KeyChanges {
+    pub fn between(old: &AuthorizedKeys, new: &AuthorizedKeys) -> Self {
+        let find = |keys: &AuthorizedKeys, public_key: &str| {
+            keys.keys.iter().find(|key| key.public_key == public_key).cloned()
+        };
+        let mut changes = KeyChanges::default();
+        for key in &new.keys {
+            match find(old, &key.public_key) {
+                None => changes.added.push(key.describe()),
+                Some(previous) if previous != *key => changes.updated.push(key.describe()),
+                Some(_) => {}
+            }
+        }
+        for key in &old.keys {
+            if find(new, &key.public_key).is_none() {
+                changes.removed.push(key.describe());
+            }
+        }
+        changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.updated.is_empty()
+    }
+}
+
+impl WatchedKeys {
+    /// Loads the keys, starting with none when the file is missing or invalid;
+    /// keys added to the file later are picked up by `reload_if_changed`.
+    pub fn load(path: &str) -> Self {
+        let stamp = file_stamp(path);
+        let keys = AuthorizedKeys::load_from_file(path).unwrap_or_else(|e| {
+            tracing::warn!("No usable authorized keys file at {} ({}), starting with empty key set", path, e);
+            AuthorizedKeys::new()
+        });
+        WatchedKeys {
+            path: path.to_string(),
+            keys: RwLock::new(Arc::new(keys)),
+            stamp: Mutex::new(stamp),
+        }
+    }
+
+    pub fn current(&self) -> Arc<AuthorizedKeys> {
+        self.keys.read().expect("authorized keys lock poisoned").clone()
+    }
+
+    /// Reloads the file if its inode, modification time or length changed
+    /// since it was last read, logging the keys added, removed or updated. If the new
+    /// file cannot be read or parsed, the current keys stay in place.
+    pub fn reload_if_changed(&self) -> Option<KeyChanges> {
+        let mut stamp = self.stamp.lock().expect("authorized keys stamp lock poisoned");
+        // Take the stamp before reading, so a write racing the read is seen
+        // as another change on the next check.
+        let new_stamp = file_stamp(&self.path);
+        if new_stamp == *stamp {
+            return None;
+        }
+        *stamp = new_stamp;
+
+        let new_keys = match AuthorizedKeys::load_from_file(&self.path) {
+            Ok(keys) => keys,
+            Err(e) => {
+                tracing::error!("Keeping current authorized keys, failed to reload {}: {}", self.path, e);
+                return None;
+            }
+        };
+
+        let mut keys = self.keys.write().expect("authorized keys lock poisoned");
+        let changes = KeyChanges::between(&keys, &new_keys);
+        *keys = Arc::new(new_keys);
+        if changes.is_empty() {
+            tracing::debug!("Reloaded {}, no key changes", self.path);
+        }
+        for key in &changes.added {
+            tracing::info!("Authorized key added: {}", key);
+        }
+        for key in &changes.removed {
+            tracing::info!("Authorized key removed: {}", key);
+        }
+        for key in &changes.updated {
+            tracing::info!("Authorized key updated: {}", key);
+        }
+        Some(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::{Duration as ChronoDuration, Utc};
+    use tempfile::TempDir;
+
+    use crate::auth::keys::Scope;
+
+    #[test]
+    fn test_reload_swaps_keys_and_keeps_them_on_bad_file() {
+        let tmp_dir = TempDir::new().unwrap();
+        let path = format!("{}/authorized_keys.json", tmp_dir.path().to_str().unwrap());
+        let expiry = Utc::now() + ChronoDuration::hours(1);
+
+        let watched = WatchedKeys::load(&path);
+        assert!(watched.current().keys.is_empty());
+        assert_eq!(watched.reload_if_changed(), None);
+
+        let mut keys = AuthorizedKeys::new();
+        keys.add_key("alice".to_string(), "aa".to_string(), expiry, Scope::DEFAULT.to_vec());
+        keys.add_key("bob".to_string(), "bb".to_string(), expiry, Scope::DEFAULT.to_vec());
+        keys.save_to_file(&path).unwrap();
+        let changes = watched.reload_if_changed().unwrap();
+        assert_eq!(changes.added.len(), 2);
+        assert!(watched.current().is_key_authorized("aa"));
+
+        keys.keys.remove(1);
+        keys.keys[0].scopes = vec![Scope::Read];
+        keys.add_key("carol".to_string(), "cc".to_string(), expiry, vec![Scope::Read]);
+        keys.save_to_file(&path).unwrap();
+        let changes = watched.reload_if_changed().unwrap();
+        assert!(changes.added[0].starts_with("carol ("));
+        assert!(changes.removed[0].starts_with("bob ("));
+        assert!(changes.updated[0].starts_with("alice ("));
+        assert!(!watched.current().is_key_authorized("bb"));
+
+        fs::write(&path, "{ not json").unwrap();
+        assert_eq!(watched.reload_if_changed(), None);
+        assert!(watched.current().is_key_authorized("cc"));
+    }
+
+    #[test]
+    fn test_reload_sees_rename_with_same_mtime_and_length() {
+        let tmp_dir = TempDir::new().unwrap();
+        let path = format!("{}/authorized_keys.json", tmp_dir.path().to_str().unwrap());
+        let expiry = Utc::now() + ChronoDuration::hours(1);
+
+        let mut keys = AuthorizedKeys::new();
+        keys.add_key("alice".to_string(), "aa".to_string(), expiry, Scope::DEFAULT.to_vec());
+        keys.save_to_file(&path).unwrap();
+        let watched = WatchedKeys::load(&path);
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+
+        keys.keys[0].public_key = "bb".to_string();
+        let replacement = format!("{}.new", path);
+        keys.save_to_file(&replacement).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&replacement)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        fs::rename(&replacement, &path).unwrap();
+
+        assert!(watched.reload_if_changed().is_some());
+        assert!(watched.current().is_key_authorized("bb"));
+    }
+}
@@ -4,26 +4,83 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use sha3::{Digest, Sha3_256};
 
+use crate::blockchain::canonical::push_field;
+use crate::blockchain::merkle::{MerkleTree, ProofStep};
 use crate::blockchain::transaction::BlockTransaction;
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Block {
+    /// How the block is hashed; see `Block::hash`. Blocks stored before the
+    /// field existed are version 1.
+    #[serde(default = "Block::legacy_version")]
+    pub block_version: u32,
     pub index: u64,
     pub previous_hash: String,
     pub timestamp: u128,
+    /// Merkle root of the transaction ids. Version 1 blocks have none and
+    /// hash their full transaction list instead.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub merkle_root: String,
     pub transactions: Vec<BlockTransaction>,
     pub hash: String,
 }
 
+/// Proof that a transaction is included in a block, checkable without the
+/// rest of the block: the header fields that make up the block hash, and the
+/// Merkle path from the transaction id to the header's root.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct TransactionProof {
+    pub block_version: u32,
+    pub block_index: u64,
+    pub previous_hash: String,
+    pub timestamp: u128,
+    pub merkle_root: String,
+    pub block_hash: String,
+    pub position: usize,
+    pub transaction_id: String,
+    pub proof: Vec<ProofStep>,
+}
+
+impl TransactionProof {
+    /// Checks that the header hashes to `block_hash` and that the Merkle path
+    /// leads from `transaction_id` to `merkle_root`.
+    #[allow(dead_code)] // public API
+    pub fn verify(&self) -> bool {
+        let header_hash = Block::hash_header(
+            self.block_version,
+            self.block_index,
+            &self.previous_hash,
+            self.timestamp,
+            &self.merkle_root,
+        );
+        let decode = |hash: &str| -> Option<[u8; 32]> { hex::decode(hash).ok()?.try_into().ok() };
+        match (decode(&self.transaction_id), decode(&self.merkle_root)) {
+            (Some(leaf), Some(root)) => {
+                header_hash == self.block_hash && MerkleTree::verify(leaf, &self.proof, root)
+            }
+            _ => false,
+        }
+    }
+}
+
 impl Block {
+    /// The version new blocks are created with.
+    pub const CURRENT_VERSION: u32 = 2;
+
+    fn legacy_version() -> u32 {
+        1
+    }
+
     pub fn new(previous_block: &Block, transactions: Vec<BlockTransaction>) -> Self {
         let index = previous_block.index + 1;
         let previous_hash = previous_block.hash();
 
         let mut block = Block {
+            block_version: Block::CURRENT_VERSION,
             index,
             previous_hash,
             timestamp: Block::cur_microtime(),
+            merkle_root: Block::compute_merkle_root(&transactions),
             transactions,
             hash: String::new(),
         };
@@ -36,9 +93,11 @@ impl Block {
             super::transaction::TransactionType::Init { data: init_data },
         )];
         let mut block = Block {
+            block_version: Block::CURRENT_VERSION,
             index: 0,
             previous_hash: String::from("0"),
             timestamp: Block::cur_microtime(),
+            merkle_root: Block::compute_merkle_root(&transactions),
             transactions,
             hash: String::new(),
         };
@@ -46,15 +105,69 @@ impl Block {
         block
     }
 
+    /// The block hash.
+    ///
+    /// Version 2 hashes a header of length-prefixed fields (version, index,
+    /// previous hash, timestamp and Merkle root), and the Merkle leaves are
+    /// hashes of canonical transaction JSON. Version 1 concatenated the
+    /// index, previous hash, timestamp and serde_json encoding of the
+    /// transactions without separators; it is only kept so that existing
+    /// chains still validate.
     pub fn hash(&self) -> String {
-        let mut hasher = Sha3_256::new();
-        let tx_json = serde_json::to_string(&self.transactions).expect("transactions must be serializable");
-        let data = format!(
-            "{}{}{}{}",
-            self.index, self.previous_hash, self.timestamp, tx_json
-        );
-        hasher.update(data.as_bytes());
-        hex::encode(hasher.finalize())
+        if self.block_version == 1 {
+            let tx_json = serde_json::to_string(&self.transactions).expect("transactions must be serializable");
+            let data = format!("{}{}{}{}", self.index, self.previous_hash, self.timestamp, tx_json);
+            return hex::encode(Sha3_256::digest(data.as_bytes()));
+        }
+        Block::hash_header(
+            self.block_version,
+            self.index,
+            &self.previous_hash,
+            self.timestamp,
+            &self.merkle_root,
+        )
+    }
+
+    /// Hash of a block header that commits to its transactions by Merkle root.
+    pub fn hash_header(version: u32, index: u64, previous_hash: &str, timestamp: u128, merkle_root: &str) -> String {
+        let mut preimage = Vec::new();
+        push_field(&mut preimage, b"deckforge-block");
+        push_field(&mut preimage, &version.to_be_bytes());
+        push_field(&mut preimage, &index.to_be_bytes());
+        push_field(&mut preimage, previous_hash.as_bytes());
+        push_field(&mut preimage, &timestamp.to_be_bytes());
+        push_field(&mut preimage, merkle_root.as_bytes());
+        hex::encode(Sha3_256::digest(&preimage))
+    }
+
+    /// The Merkle leaves of a block: the transaction digests.
+    fn transaction_digests(transactions: &[BlockTransaction]) -> Vec<[u8; 32]> {
+        transactions.iter().map(BlockTransaction::digest).collect()
+    }
+
+    pub fn compute_merkle_root(transactions: &[BlockTransaction]) -> String {
+        hex::encode(MerkleTree::root(&Block::transaction_digests(transactions)))
+    }
+
+    /// Inclusion proof for the transaction at `position`, or `None` when
+    /// there is no such transaction or the block is version 1.
+    pub fn transaction_proof(&self, position: usize) -> Option<TransactionProof> {
+        if self.merkle_root.is_empty() {
+            return None;
+        }
+        let leaves = Block::transaction_digests(&self.transactions);
+        let proof = MerkleTree::proof(&leaves, position)?;
+        Some(TransactionProof {
+            block_version: self.block_version,
+            block_index: self.index,
+            previous_hash: self.previous_hash.clone(),
+            timestamp: self.timestamp,
+            merkle_root: self.merkle_root.clone(),
+            block_hash: self.hash.clone(),
+            position,
+            transaction_id: hex::encode(leaves[position]),
+            proof,
+        })
     }
 
     fn cur_microtime() -> u128 {
@@ -64,3 +177,91 @@ impl Block {
             .as_micros()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::Value;
+
+    use crate::blockchain::transaction::TransactionType;
+
+    fn test_block() -> Block {
+        let genesis = Block::new_genesis(Value::Null);
+        let transactions = (0..3)
+            .map(|i| {
+                BlockTransaction::new(TransactionType::RevealShuffle {
+                    series_id: format!("SERIES-{}", i),
+                    private_salt: String::new(),
+                })
+            })
+            .collect();
+        Block::new(&genesis, transactions)
+    }
+
+    #[test]
+    fn test_transaction_proof_verifies() {
+        let block = test_block();
+        for position in 0..3 {
+            let proof = block.transaction_proof(position).unwrap();
+            assert_eq!(proof.transaction_id, block.transactions[position].id());
+            assert!(proof.verify());
+        }
+        assert!(block.transaction_proof(3).is_none());
+    }
+
+    #[test]
+    fn test_tampered_proof_fails() {
+        let block = test_block();
+        let mut proof = block.transaction_proof(1).unwrap();
+        proof.timestamp += 1;
+        assert!(!proof.verify());
+
+        let mut proof = block.transaction_proof(1).unwrap();
+        proof.transaction_id = block.transactions[0].id();
+        assert!(!proof.verify());
+    }
+
+    #[test]
+    fn test_legacy_block_hashes_transactions() {
+        let mut block = test_block();
+        block.block_version = 1;
+        block.merkle_root = String::new();
+        block.hash = block.hash();
+        assert!(block.transaction_proof(0).is_none());
+
+        let mut json = serde_json::to_value(&block).unwrap();
+        assert!(json.get("merkle_root").is_none());
+        json.as_object_mut().unwrap().remove("block_version");
+        let stored: Block = serde_json::from_value(json).unwrap();
+        assert_eq!(stored.block_version, 1);
+        assert_eq!(stored.hash(), block.hash);
+
+        block.transactions.pop();
+        assert_ne!(block.hash(), block.hash);
+    }
+
+    #[test]
+    fn test_header_fields_are_length_prefixed() {
+        // "1" + "23" and "12" + "3" concatenate identically in version 1.
+        let mut v1_a = test_block();
+        v1_a.block_version = 1;
+        v1_a.merkle_root = String::new();
+        let mut v1_b = v1_a.clone();
+        (v1_a.index, v1_a.previous_hash) = (1, "23".to_string());
+        (v1_b.index, v1_b.previous_hash) = (12, "3".to_string());
+        assert_eq!(v1_a.hash(), v1_b.hash());
+
+        let v2_a = Block::hash_header(2, 1, "23", 4, "root");
+        let v2_b = Block::hash_header(2, 12, "3", 4, "root");
+        assert_ne!(v2_a, v2_b);
+    }
+
+    #[test]
+    fn test_transaction_digest_ignores_key_order() {
+        let a: Value = serde_json::from_str(r#"{"id":"S","name":"Series"}"#).unwrap();
+        let b: Value = serde_json::from_str(r#"{"name":"Series","id":"S"}"#).unwrap();
+        let tx = |data| BlockTransaction::new(TransactionType::ReleaseSet { id: "h".to_string(), data });
+        assert_eq!(tx(a).digest(), tx(b).digest());
+    }
+}
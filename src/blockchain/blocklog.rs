@@ -0,0 +1,280 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::blockchain::block::Block;
+use crate::blockchain::storage::{sync_parent_dir, ChainStorage};
+use crate::error::{DeckForgeError, Result};
+
+/// Append-only block storage.
+///
+/// Each block is stored as one frame: a 4 byte magic, the payload length and
+/// the CRC32 of the payload (both little-endian u32), then the block as
+/// compact JSON. Appends are fsynced, so a crash can at worst leave a torn
+/// final frame, which `load_blocks` cuts off to recover the last complete
+/// block. A bad frame followed by more data is corruption, not a crash, and
+/// `load_blocks` refuses it.
+#[derive(Clone)]
+pub struct BlockLog {
+    path: String,
+}
+
+/// The blocks read from a log and how much of the file they cover.
+pub struct BlockLogContents {
+    pub blocks: Vec<Block>,
+    pub valid_len: u64,
+    pub file_len: u64,
+    /// Whether the unreadable bytes after `valid_len` are more than a torn final frame.
+    pub corrupt: bool,
+}
+
+impl BlockLog {
+    const MAGIC: [u8; 4] = *b"DFBL";
+    const HEADER_LEN: usize = 12;
+
+    pub fn new(path: &str) -> Self {
+        BlockLog { path: path.to_string() }
+    }
+
+    /// Reads every complete frame from the start of the log, stopping at the
+    /// first frame that is incomplete, fails its checksum or cannot be parsed.
+    /// That frame only counts as torn, rather than corrupt, when the file ends
+    /// before the frame's declared length and no later frame can be read.
+    pub fn read(path: &str) -> Result<BlockLogContents> {
+        let mut data = Vec::new();
+        File::open(path)?.read_to_end(&mut data)?;
+
+        let mut blocks = Vec::new();
+        let mut offset = 0;
+        while let Some((block, frame_len)) = BlockLog::decode_frame(&data[offset..]) {
+            blocks.push(block);
+            offset += frame_len;
+        }
+
+        let rest = &data[offset..];
+        Ok(BlockLogContents {
+            blocks,
+            valid_len: offset as u64,
+            file_len: data.len() as u64,
+            corrupt: !rest.is_empty() && !BlockLog::is_torn_frame(rest),
+        })
+    }
+
+    fn encode_frame(block: &Block) -> Result<Vec<u8>> {
+        let payload = serde_json::to_vec(block)?;
+        let len = u32::try_from(payload.len()).map_err(|_| DeckForgeError::Validation {
+            reason: format!("Block {} is too large for the block log", block.index),
+        })?;
+        let mut frame = Vec::with_capacity(BlockLog::HEADER_LEN + payload.len());
+        frame.extend_from_slice(&BlockLog::MAGIC);
+        frame.extend_from_slice(&len.to_le_bytes());
+        frame.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+        frame.extend_from_slice(&payload);
+        Ok(frame)
+    }
+
+    fn decode_frame(data: &[u8]) -> Option<(Block, usize)> {
+        let header = data.get(..BlockLog::HEADER_LEN)?;
+        if header[..4] != BlockLog::MAGIC {
+            return None;
+        }
+        let len = u32::from_le_bytes(header[4..8].try_into().ok()?) as usize;
+        let checksum = u32::from_le_bytes(header[8..12].try_into().ok()?);
+
+        let payload = data.get(BlockLog::HEADER_LEN..BlockLog::HEADER_LEN + len)?;
+        if crc32fast::hash(payload) != checksum {
+            return None;
+        }
+        let block = serde_json::from_slice(payload).ok()?;
+        Some((block, BlockLog::HEADER_LEN + len))
+    }
+
+    /// Whether an unreadable frame is the final one, cut short by a crash
+    /// during its append. A final frame with all its bytes present but a bad
+    /// checksum was fully written, so it is corruption, not a torn append.
+    /// So is a damaged length field that runs past the end of the file while
+    /// complete frames still follow it.
+    fn is_torn_frame(data: &[u8]) -> bool {
+        let cut_short = match data.get(..BlockLog::HEADER_LEN) {
+            None => true,
+            Some(header) => {
+                let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+                header[..4] == BlockLog::MAGIC && BlockLog::HEADER_LEN + len > data.len()
+            }
+        };
+        cut_short && !BlockLog::has_later_frame(data)
+    }
+
+    /// Whether a readable frame starts anywhere after the first byte.
+    fn has_later_frame(data: &[u8]) -> bool {
+        (1..data.len()).any(|start| {
+            data[start..].starts_with(&BlockLog::MAGIC) && BlockLog::decode_frame(&data[start..]).is_some()
+        })
+    }
+}
+
+impl ChainStorage for BlockLog {
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Reads the log, truncating a torn final frame.
+    fn load_blocks(&self) -> Result<Vec<Block>> {
+        if !Path::new(&self.path).exists() {
+            return Ok(Vec::new());
+        }
+
+        let contents = BlockLog::read(&self.path)?;
+        if contents.corrupt {
+            return Err(DeckForgeError::CorruptChain {
+                path: self.path.clone(),
+                reason: format!("unreadable block frame at byte {}", contents.valid_len),
+            });
+        }
+        if contents.valid_len < contents.file_len {
+            tracing::warn!(
+                "Block log {} has a torn tail: keeping {} complete blocks, discarding {} bytes",
+                self.path,
+                contents.blocks.len(),
+                contents.file_len - contents.valid_len
+            );
+            let file = OpenOptions::new().write(true).open(&self.path)?;
+            file.set_len(contents.valid_len)?;
+            file.sync_all()?;
+        }
+        Ok(contents.blocks)
+    }
+
+    fn recover_blocks(&self) -> Result<Vec<Block>> {
+        Ok(BlockLog::read(&self.path)?.blocks)
+    }
+
+    /// Writes a new log, replacing the old one only once it is fully on disk.
+    fn write_blocks(&self, blocks: &[Block]) -> Result<()> {
+        let tmp_path = format!("{}.tmp", self.path);
+        let mut file = File::create(&tmp_path)?;
+        for block in blocks {
+            file.write_all(&BlockLog::encode_frame(block)?)?;
+        }
+        file.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+        sync_parent_dir(&self.path)
+    }
+
+    /// Appends one frame and waits for it to reach the disk. A failed append
+    /// is cut back off, so the next one does not land after a partial frame.
+    fn append_block(&self, block: &Block) -> Result<()> {
+        let frame = BlockLog::encode_frame(block)?;
+        let mut file = OpenOptions::new().append(true).open(&self.path)?;
+        let len = file.metadata()?.len();
+        if let Err(e) = file.write_all(&frame).and_then(|()| file.sync_data()) {
+            let _ = file.set_len(len).and_then(|()| file.sync_data());
+            return Err(e.into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::Value;
+    use tempfile::TempDir;
+
+    fn test_log() -> (TempDir, String, Vec<Block>) {
+        let tmp_dir = TempDir::new().unwrap();
+        let path = format!("{}/blockchain.log", tmp_dir.path().to_str().unwrap());
+        let genesis = Block::new_genesis(Value::Null);
+        let block1 = Block::new(&genesis, vec![]);
+        (tmp_dir, path, vec![genesis, block1])
+    }
+
+    #[test]
+    fn test_append_and_reopen() {
+        let (_tmp, path, blocks) = test_log();
+        let log = BlockLog::new(&path);
+        log.write_blocks(&blocks[..1]).unwrap();
+        log.append_block(&blocks[1]).unwrap();
+
+        let read = BlockLog::new(&path).load_blocks().unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[1].hash, blocks[1].hash);
+    }
+
+    #[test]
+    fn test_torn_tail_is_truncated() {
+        let (_tmp, path, blocks) = test_log();
+        let log = BlockLog::new(&path);
+        log.write_blocks(&blocks).unwrap();
+        let full_len = fs::metadata(&path).unwrap().len();
+
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(full_len - 5).unwrap();
+
+        let read = log.load_blocks().unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(
+            fs::metadata(&path).unwrap().len(),
+            BlockLog::encode_frame(&blocks[0]).unwrap().len() as u64
+        );
+
+        log.append_block(&blocks[1]).unwrap();
+        let read = log.load_blocks().unwrap();
+        assert_eq!(read.len(), 2);
+    }
+
+    #[test]
+    fn test_corrupt_frame_fails_checksum() {
+        let (_tmp, path, blocks) = test_log();
+        BlockLog::new(&path).write_blocks(&blocks).unwrap();
+
+        let mut data = fs::read(&path).unwrap();
+        let last = data.len() - 2;
+        data[last] ^= 0xff;
+        fs::write(&path, &data).unwrap();
+
+        let contents = BlockLog::read(&path).unwrap();
+        assert_eq!(contents.blocks.len(), 1);
+        assert!(contents.valid_len < contents.file_len);
+        assert!(contents.corrupt);
+
+        let err = BlockLog::new(&path).load_blocks().err().unwrap();
+        assert!(matches!(err, DeckForgeError::CorruptChain { .. }));
+        assert_eq!(fs::read(&path).unwrap(), data);
+    }
+
+    #[test]
+    fn test_corrupt_length_field_is_refused() {
+        let (_tmp, path, mut blocks) = test_log();
+        blocks.push(Block::new(&blocks[1], vec![]));
+        BlockLog::new(&path).write_blocks(&blocks).unwrap();
+
+        let mut data = fs::read(&path).unwrap();
+        let frame1 = BlockLog::encode_frame(&blocks[0]).unwrap().len();
+        data[frame1 + 7] ^= 0x40;
+        fs::write(&path, &data).unwrap();
+
+        let contents = BlockLog::read(&path).unwrap();
+        assert_eq!(contents.blocks.len(), 1);
+        assert!(contents.corrupt);
+
+        let err = BlockLog::new(&path).load_blocks().err().unwrap();
+        assert!(matches!(err, DeckForgeError::CorruptChain { .. }));
+        assert_eq!(fs::read(&path).unwrap(), data);
+    }
+
+    #[test]
+    fn test_corrupt_middle_frame_is_refused() {
+        let (_tmp, path, blocks) = test_log();
+        BlockLog::new(&path).write_blocks(&blocks).unwrap();
+
+        let mut data = fs::read(&path).unwrap();
+        data[BlockLog::HEADER_LEN + 1] ^= 0xff;
+        fs::write(&path, &data).unwrap();
+
+        let err = BlockLog::new(&path).load_blocks().err().unwrap();
+        assert!(matches!(err, DeckForgeError::CorruptChain { .. }));
+        assert_eq!(fs::read(&path).unwrap(), data);
+    }
+}
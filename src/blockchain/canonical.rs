@@ -0,0 +1,78 @@
+use serde::Serialize;
+use serde_json::Value;
+
+/// Canonical JSON: compact, with the keys of every object sorted
+/// byte-wise. The output depends only on the value, never on the order
+/// fields were declared or inserted in, so it is safe to hash whether or not
+/// serde_json's `preserve_order` feature is enabled.
+pub fn to_canonical_json<T: Serialize>(value: &T) -> serde_json::Result<String> {
+    let value = serde_json::to_value(value)?;
+    let mut out = String::new();
+    write_value(&value, &mut out)?;
+    Ok(out)
+}
+
+fn write_value(value: &Value, out: &mut String) -> serde_json::Result<()> {
+    match value {
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(item, out)?;
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+            out.push('{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::to_string(key)?);
+                out.push(':');
+                write_value(item, out)?;
+            }
+            out.push('}');
+        }
+        scalar => out.push_str(&serde_json::to_string(scalar)?),
+    }
+    Ok(())
+}
+
+/// Appends a field to a hash preimage as its length (u64, big-endian)
+/// followed by its bytes, so no two field sequences share an encoding.
+pub fn push_field(preimage: &mut Vec<u8>, field: &[u8]) {
+    preimage.extend_from_slice(&(field.len() as u64).to_be_bytes());
+    preimage.extend_from_slice(field);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn test_keys_are_sorted_at_every_level() {
+        let value = json!({"b": [{"z": 1, "a": "x"}], "a": null, "B": true});
+        assert_eq!(
+            to_canonical_json(&value).unwrap(),
+            r#"{"B":true,"a":null,"b":[{"a":"x","z":1}]}"#
+        );
+    }
+
+    #[test]
+    fn test_fields_are_unambiguous() {
+        let mut first = Vec::new();
+        push_field(&mut first, b"12");
+        push_field(&mut first, b"3");
+        let mut second = Vec::new();
+        push_field(&mut second, b"1");
+        push_field(&mut second, b"23");
+        assert_ne!(first, second);
+    }
+}
@@ -1,10 +1,13 @@
 use std::fs;
 use std::path::Path;
 
+use rusqlite::ErrorCode;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::blockchain::block::Block;
+use crate::blockchain::ledger::{CardLedger, LedgerUndo};
+use crate::blockchain::storage::ChainStorage;
 use crate::blockchain::transaction::{BlockTransaction, TransactionType};
 use crate::error::{DeckForgeError, Result};
 
@@ -14,26 +17,79 @@ pub struct BlockChain {
 }
 
 impl BlockChain {
-    #[allow(dead_code)] // blockchain constant for transaction addresses
     pub const NULL_ADDRESS: &'static str = "0x0000000000000000000000000000000000000000";
 
-    pub fn new(storage_path: &str, init_data: Value) -> Result<Self> {
-        match BlockChain::load(storage_path) {
+    /// Loads the chain from `storage`, creating a new one only when nothing
+    /// has been stored yet. An invalid chain file is quarantined, never overwritten.
+    pub fn new(storage: &dyn ChainStorage, init_data: Value) -> Result<Self> {
+        match BlockChain::load(storage) {
             Ok(blockchain) => Ok(blockchain),
-            Err(_) => BlockChain::init(storage_path, init_data),
+            Err(DeckForgeError::BlockchainNotFound { .. }) => {
+                BlockChain::check_not_quarantined(storage.path())?;
+                BlockChain::init(storage, init_data)
+            }
+            Err(e) => Err(BlockChain::quarantine_invalid(storage.path(), e)),
+        }
+    }
+
+    pub fn load(storage: &dyn ChainStorage) -> Result<Self> {
+        let blocks = storage.load_blocks()?;
+        if blocks.is_empty() {
+            return Err(DeckForgeError::BlockchainNotFound {
+                path: storage.path().to_string(),
+            });
         }
+
+        let blockchain = BlockChain { blocks };
+        blockchain.validate()?;
+        Ok(blockchain)
+    }
+
+    /// Where an invalid chain file is moved so it is kept for inspection.
+    pub fn quarantine_path(storage_path: &str) -> String {
+        format!("{}.quarantined", storage_path)
     }
 
-    pub fn load(storage_path: &str) -> Result<Self> {
-        if Path::new(storage_path).exists() {
-            let contents = fs::read_to_string(storage_path)?;
-            let blockchain: BlockChain = serde_json::from_str(&contents)?;
-            blockchain.validate()?;
-            Ok(blockchain)
-        } else {
-            Err(DeckForgeError::BlockchainNotFound {
+    /// Refuses to go on while an earlier invalid chain file is still
+    /// quarantined, so the history is not silently restarted.
+    pub fn check_not_quarantined(storage_path: &str) -> Result<()> {
+        let quarantined = BlockChain::quarantine_path(storage_path);
+        if Path::new(&quarantined).exists() {
+            return Err(DeckForgeError::ChainQuarantined {
                 path: storage_path.to_string(),
-            })
+                quarantined,
+                reason: "it failed to load earlier".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Moves the chain file aside after `err` showed it to be invalid. IO
+    /// errors, SQLite errors other than a damaged database, and a missing
+    /// file say nothing about the file's contents and are returned unchanged.
+    pub fn quarantine_invalid(storage_path: &str, err: DeckForgeError) -> DeckForgeError {
+        match err {
+            DeckForgeError::Io(_) | DeckForgeError::BlockchainNotFound { .. } => err,
+            DeckForgeError::Sqlite(ref e)
+                if !matches!(
+                    e.sqlite_error_code(),
+                    Some(ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase)
+                ) =>
+            {
+                err
+            }
+            _ => {
+                let quarantined = BlockChain::quarantine_path(storage_path);
+                if let Err(e) = fs::rename(storage_path, &quarantined) {
+                    return e.into();
+                }
+                tracing::error!("Quarantined invalid blockchain {} as {}: {}", storage_path, quarantined, err);
+                DeckForgeError::ChainQuarantined {
+                    path: storage_path.to_string(),
+                    quarantined,
+                    reason: err.to_string(),
+                }
+            }
         }
     }
 
@@ -42,71 +98,133 @@ impl BlockChain {
             return Err(DeckForgeError::EmptyChain);
         }
 
-        let genesis = &self.blocks[0];
-        if genesis.index != 0 {
+        let mut ledger = CardLedger::new();
+        for index in 0..self.blocks.len() {
+            self.validate_block(index, &mut ledger)?;
+        }
+        Ok(())
+    }
+
+    /// Drops every block from the first invalid one onwards and returns why
+    /// that block was invalid, or `None` when the whole chain is valid.
+    pub fn truncate_to_valid(&mut self) -> Option<DeckForgeError> {
+        let mut ledger = CardLedger::new();
+        for index in 0..self.blocks.len() {
+            if let Err(e) = self.validate_block(index, &mut ledger) {
+                self.blocks.truncate(index);
+                return Some(e);
+            }
+        }
+        None
+    }
+
+    /// Checks one block against its predecessor and applies its transactions
+    /// to `ledger`, which must hold every earlier block.
+    fn validate_block(&self, i: usize, ledger: &mut CardLedger) -> Result<()> {
+        let block = &self.blocks[i];
+        if i == 0 {
+            if block.index != 0 {
+                return Err(DeckForgeError::Validation {
+                    reason: format!("Genesis block has index {}, expected 0", block.index),
+                });
+            }
+            if block.previous_hash != "0" {
+                return Err(DeckForgeError::Validation {
+                    reason: "Genesis block has invalid previous_hash".to_string(),
+                });
+            }
+        }
+
+        if block.block_version == 0 || block.block_version > Block::CURRENT_VERSION {
+            return Err(DeckForgeError::Validation {
+                reason: format!("Block {} has unsupported version {}", block.index, block.block_version),
+            });
+        }
+        if block.block_version > 1 && block.merkle_root.is_empty() {
+            return Err(DeckForgeError::Validation {
+                reason: format!("Block {} is missing its merkle root", block.index),
+            });
+        }
+        if block.block_version == 1 && !block.merkle_root.is_empty() {
+            return Err(DeckForgeError::Validation {
+                reason: format!("Block {} is version 1 but has a merkle root", block.index),
+            });
+        }
+        if i > 0 && block.block_version < self.blocks[i - 1].block_version {
             return Err(DeckForgeError::Validation {
-                reason: format!("Genesis block has index {}, expected 0", genesis.index),
+                reason: format!(
+                    "Block {} has version {}, older than the version {} before it",
+                    block.index,
+                    block.block_version,
+                    self.blocks[i - 1].block_version
+                ),
             });
         }
-        if genesis.previous_hash != "0" {
+
+        let recomputed = block.hash();
+        if block.hash != recomputed {
             return Err(DeckForgeError::Validation {
-                reason: "Genesis block has invalid previous_hash".to_string(),
+                reason: format!(
+                    "Block {} hash mismatch: stored={}, computed={}",
+                    block.index, block.hash, recomputed
+                ),
             });
         }
 
-        for (i, block) in self.blocks.iter().enumerate() {
-            let recomputed = block.hash();
-            if block.hash != recomputed {
+        if !block.merkle_root.is_empty() {
+            let computed = Block::compute_merkle_root(&block.transactions);
+            if block.merkle_root != computed {
                 return Err(DeckForgeError::Validation {
                     reason: format!(
-                        "Block {} hash mismatch: stored={}, computed={}",
-                        block.index, block.hash, recomputed
+                        "Block {} merkle root mismatch: stored={}, computed={}",
+                        block.index, block.merkle_root, computed
                     ),
                 });
             }
+        }
 
-            if block.index != i as u64 {
+        if block.index != i as u64 {
+            return Err(DeckForgeError::Validation {
+                reason: format!(
+                    "Block index {} at position {}", block.index, i
+                ),
+            });
+        }
+
+        if i > 0 {
+            let prev = &self.blocks[i - 1];
+            if block.previous_hash != prev.hash {
                 return Err(DeckForgeError::Validation {
                     reason: format!(
-                        "Block index {} at position {}", block.index, i
+                        "Block {} previous_hash doesn't match block {} hash",
+                        block.index,
+                        prev.index
                     ),
                 });
             }
-
-            if i > 0 {
-                let prev = &self.blocks[i - 1];
-                if block.previous_hash != prev.hash {
-                    return Err(DeckForgeError::Validation {
-                        reason: format!(
-                            "Block {} previous_hash doesn't match block {} hash",
-                            block.index,
-                            prev.index
-                        ),
-                    });
-                }
-            }
         }
 
+        for tx in &block.transactions {
+            ledger.apply_transaction(tx)?;
+        }
         Ok(())
     }
 
-    pub fn init(storage_path: &str, init_data: Value) -> Result<Self> {
+    pub fn init(storage: &dyn ChainStorage, init_data: Value) -> Result<Self> {
         let genesis_block = Block::new_genesis(init_data);
 
         let blockchain = BlockChain {
             blocks: vec![genesis_block],
         };
 
-        let serialized = serde_json::to_string(&blockchain)?;
-        fs::write(storage_path, serialized)?;
+        storage.write_blocks(&blockchain.blocks)?;
 
         Ok(blockchain)
     }
 
-    pub fn save(&self, storage_path: &str) -> Result<()> {
-        let contents = serde_json::to_string_pretty(self)?;
-        fs::write(storage_path, contents)?;
-        Ok(())
+    #[allow(dead_code)] // public API
+    pub fn save(&self, storage: &dyn ChainStorage) -> Result<()> {
+        storage.write_blocks(&self.blocks)
     }
 
     #[allow(dead_code)] // public API
@@ -127,18 +245,59 @@ impl BlockChain {
         self.blocks.get(index as usize)
     }
 
-    #[allow(dead_code)] // public API
     pub fn get_blocks(&self) -> &[Block] {
         &self.blocks
     }
 
+    /// Up to `limit` blocks starting at index `from`; empty past the tip.
+    pub fn get_blocks_from(&self, from: u64, limit: usize) -> &[Block] {
+        let start = (from as usize).min(self.blocks.len());
+        let end = start.saturating_add(limit).min(self.blocks.len());
+        &self.blocks[start..end]
+    }
+
+    /// The block with the given hash, searching back from the tip.
+    pub fn get_block_by_hash(&self, hash: &str) -> Option<&Block> {
+        self.blocks.iter().rev().find(|block| block.hash.eq_ignore_ascii_case(hash))
+    }
+
+    pub fn latest_block(&self) -> Option<&Block> {
+        self.blocks.last()
+    }
+
+    #[allow(dead_code)] // public API
     pub fn add_block(&mut self, transactions: Vec<BlockTransaction>) -> Result<()> {
-        let previous = self.blocks.last().ok_or(DeckForgeError::EmptyChain)?;
-        let block = Block::new(previous, transactions);
+        let mut ledger = CardLedger::from_blockchain(self)?;
+        self.add_block_with_ledger(transactions, &mut ledger)
+    }
+
+    /// Appends a block, checking its transactions against an already-built
+    /// ledger instead of replaying the whole chain. The ledger is only
+    /// updated when every transaction is accepted.
+    pub fn add_block_with_ledger(
+        &mut self,
+        transactions: Vec<BlockTransaction>,
+        ledger: &mut CardLedger,
+    ) -> Result<()> {
+        let (block, _) = self.prepare_block(transactions, ledger)?;
         self.blocks.push(block);
         Ok(())
     }
 
+    /// Builds the next block without appending it and applies its
+    /// transactions to the ledger, returning the undo log for the caller to
+    /// revert with if the block is not appended after all. On error the
+    /// ledger is left unchanged.
+    pub fn prepare_block(
+        &self,
+        transactions: Vec<BlockTransaction>,
+        ledger: &mut CardLedger,
+    ) -> Result<(Block, LedgerUndo)> {
+        let previous = self.blocks.last().ok_or(DeckForgeError::EmptyChain)?;
+        let undo = ledger.apply_block(&transactions)?;
+        Ok((Block::new(previous, transactions), undo))
+    }
+
     #[allow(dead_code)] // public API
     pub fn get_blocks_by_transaction_type(&self, transaction_type: TransactionType) -> Vec<&Block> {
         self.blocks
@@ -157,6 +316,9 @@ impl BlockChain {
 mod tests {
     use super::*;
 
+    use crate::blockchain::storage::JsonFileStorage;
+    use crate::crypto::keypair::KeyPair;
+
     fn test_chain() -> BlockChain {
         let genesis = Block::new_genesis(Value::Null);
         let block1 = Block::new(&genesis, vec![]);
@@ -185,6 +347,66 @@ mod tests {
         assert!(err.contains("hash mismatch"));
     }
 
+    #[test]
+    fn test_validate_tampered_transactions() {
+        let mut chain = test_chain();
+        chain.blocks[1].transactions.push(BlockTransaction::new(TransactionType::Init {
+            data: Value::Null,
+        }));
+        let err = chain.validate().unwrap_err().to_string();
+        assert!(err.contains("merkle root mismatch"));
+    }
+
+    /// A chain as stored before blocks were versioned: no `block_version`,
+    /// no `merkle_root`, hashed over the serde_json transactions.
+    fn legacy_chain() -> BlockChain {
+        let mut chain = test_chain();
+        let mut previous_hash = "0".to_string();
+        for block in chain.blocks.iter_mut() {
+            block.block_version = 1;
+            block.merkle_root = String::new();
+            block.previous_hash = previous_hash;
+            block.hash = block.hash();
+            previous_hash = block.hash.clone();
+        }
+        let mut json = serde_json::to_value(&chain).unwrap();
+        for block in json["blocks"].as_array_mut().unwrap() {
+            block.as_object_mut().unwrap().remove("block_version");
+        }
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_validate_legacy_blocks() {
+        let mut chain = legacy_chain();
+        assert!(chain.blocks.iter().all(|block| block.block_version == 1));
+        assert!(chain.validate().is_ok());
+
+        let block2 = Block::new(&chain.blocks[1], vec![]);
+        assert_eq!(block2.block_version, Block::CURRENT_VERSION);
+        chain.blocks.push(block2);
+        assert!(chain.validate().is_ok());
+        chain.blocks[1].merkle_root = Block::compute_merkle_root(&chain.blocks[1].transactions);
+        let err = chain.validate().unwrap_err().to_string();
+        assert!(err.contains("version 1 but has a merkle root"));
+    }
+
+    #[test]
+    fn test_validate_version_downgrade() {
+        let mut chain = test_chain();
+        let mut block2 = Block::new(&chain.blocks[1], vec![]);
+        block2.block_version = 1;
+        block2.merkle_root = String::new();
+        block2.hash = block2.hash();
+        chain.blocks.push(block2);
+        let err = chain.validate().unwrap_err().to_string();
+        assert!(err.contains("older than the version"));
+
+        chain.blocks[2].block_version = 3;
+        let err = chain.validate().unwrap_err().to_string();
+        assert!(err.contains("unsupported version 3"));
+    }
+
     #[test]
     fn test_validate_broken_linkage() {
         let mut chain = test_chain();
@@ -194,4 +416,90 @@ mod tests {
         let err = chain.validate().unwrap_err().to_string();
         assert!(err.contains("previous_hash doesn't match"));
     }
+
+    #[test]
+    fn test_get_blocks_from() {
+        let mut chain = test_chain();
+        let block2 = Block::new(&chain.blocks[1], vec![]);
+        chain.blocks.push(block2);
+
+        let page = chain.get_blocks_from(1, 5);
+        assert_eq!(page.iter().map(|b| b.index).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(chain.get_blocks_from(0, 1).len(), 1);
+        assert!(chain.get_blocks_from(3, 5).is_empty());
+        assert!(chain.get_blocks_from(u64::MAX, usize::MAX).is_empty());
+
+        let hash = chain.blocks[1].hash.to_uppercase();
+        assert_eq!(chain.get_block_by_hash(&hash).unwrap().index, 1);
+        assert!(chain.get_block_by_hash("missing").is_none());
+        assert_eq!(chain.latest_block().unwrap().index, 2);
+    }
+
+    #[test]
+    fn test_truncate_to_valid() {
+        let mut chain = test_chain();
+        let block2 = Block::new(&chain.blocks[1], vec![]);
+        chain.blocks.push(block2);
+        chain.blocks[1].timestamp += 1;
+
+        let err = chain.truncate_to_valid().unwrap();
+        assert!(err.to_string().contains("hash mismatch"));
+        assert_eq!(chain.blocks.len(), 1);
+        assert!(chain.truncate_to_valid().is_none());
+    }
+
+    #[test]
+    fn test_new_quarantines_invalid_file() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let path = format!("{}/blockchain.json", tmp_dir.path().to_str().unwrap());
+        let storage = JsonFileStorage::new(&path);
+        let mut chain = test_chain();
+        chain.blocks[1].hash = "tampered".to_string();
+        chain.save(&storage).unwrap();
+
+        let err = BlockChain::new(&storage, Value::Null).err().unwrap();
+        assert!(matches!(err, DeckForgeError::ChainQuarantined { .. }));
+        assert!(!Path::new(&path).exists());
+        assert!(Path::new(&BlockChain::quarantine_path(&path)).exists());
+
+        let err = BlockChain::new(&storage, Value::Null).err().unwrap();
+        assert!(matches!(err, DeckForgeError::ChainQuarantined { .. }));
+        assert!(!Path::new(&path).exists());
+
+        let quarantined = JsonFileStorage::new(&BlockChain::quarantine_path(&path));
+        let lenient = quarantined.recover_blocks().unwrap();
+        assert_eq!(lenient.len(), 2);
+    }
+
+    #[test]
+    fn test_add_block_rejects_unowned_transfer() {
+        let mut chain = test_chain();
+        let tx = BlockTransaction::new_transfer(
+            &KeyPair::new(),
+            "LEGACYDECK-1-1-001".to_string(),
+            "0x8ba82d54332db0c58edc1120a15409aa8cd5f7d9".to_string(),
+            0,
+        )
+        .unwrap();
+        let err = chain.add_block(vec![tx]).unwrap_err();
+        assert!(matches!(err, DeckForgeError::NotCardOwner { .. }));
+        assert_eq!(chain.blocks.len(), 2);
+    }
+
+    #[test]
+    fn test_add_block_rejects_forged_transfer() {
+        let mut chain = test_chain();
+        let mut tx = BlockTransaction::new_transfer(
+            &KeyPair::new(),
+            "LEGACYDECK-1-1-001".to_string(),
+            "0x8ba82d54332db0c58edc1120a15409aa8cd5f7d9".to_string(),
+            0,
+        )
+        .unwrap();
+        if let TransactionType::TransferCard { sender, .. } = &mut tx.transaction_type {
+            *sender = "0x8ba82d54332db0c58edc1120a15409aa8cd5f7d9".to_string();
+        }
+        let err = chain.add_block(vec![tx]).unwrap_err();
+        assert!(matches!(err, DeckForgeError::InvalidSignature { .. }));
+    }
 }
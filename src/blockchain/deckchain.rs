@@ -1,33 +1,108 @@
-use std::fs::read_to_string;
+use std::collections::HashSet;
+use std::fs::{self, read_to_string, File};
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use sha3::{Digest, Sha3_256};
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 use serde_json::Value;
+use tokio::sync::broadcast;
 
 use crate::blockchain::block::Block;
 use crate::blockchain::chain::BlockChain;
+use crate::blockchain::events::ChainEvent;
+use crate::blockchain::ledger::CardLedger;
+use crate::blockchain::storage::{sync_parent_dir, ChainStorage, JsonFileStorage, StorageKind};
 use crate::blockchain::transaction::{BlockTransaction, TransactionType};
-use crate::card::seriesreleasestate::TradingCardSeriesReleaseState;
+use crate::blockchain::txindex::{TransactionIndex, TransactionLocation, TransactionRecord};
+use crate::card::card::TradingCard;
+use crate::card::series::{CardConfig, TradingCardSeries};
+use crate::card::seriesreleasestate::{SeriesAvailability, TradingCardSeriesReleaseState};
 use crate::config::Config;
 use crate::error::{DeckForgeError, Result};
 
-#[derive(Clone, Serialize, Deserialize)]
+/// What `DeckChain::repair` recovered, and from where.
+#[derive(Clone, Serialize, Debug)]
+pub struct ChainRepair {
+    pub source: String,
+    pub kept_blocks: usize,
+    pub discarded_blocks: usize,
+    pub reason: Option<String>,
+    pub backup: Option<String>,
+}
+
+/// A minted card with its current owner.
+#[derive(Clone, Serialize, Debug)]
+pub struct CardDetails {
+    pub card_id: String,
+    #[serde(flatten)]
+    pub card: TradingCard,
+    pub owner: String,
+    pub transfers: u64,
+}
+
+/// One step in the history of a card: its mint, the pack that dealt it, or a
+/// transfer. `from` is absent for the mint.
+#[derive(Clone, Serialize, Debug)]
+pub struct CardEvent {
+    pub transaction_id: String,
+    pub transaction_type: String,
+    pub block_index: u64,
+    pub position: usize,
+    pub timestamp: u128,
+    pub from: Option<String>,
+    pub to: String,
+}
+
+/// A series' on-chain shuffle commitment and whether its salt is revealed.
+#[derive(Clone, Serialize, Debug)]
+pub struct ShuffleCommitment {
+    pub shuffle_hash: String,
+    pub salt_commitment: String,
+    pub revealed: bool,
+}
+
+/// Release progress of a series: what was minted, what is left to deal, and
+/// the shuffle it is dealt from.
+#[derive(Clone, Serialize, Debug)]
+pub struct SeriesState {
+    pub series_id: String,
+    pub packs_opened: u64,
+    #[serde(flatten)]
+    pub availability: SeriesAvailability,
+    pub shuffle: Option<ShuffleCommitment>,
+}
+
 pub struct DeckChain {
     pub data_dir: String,
     pub blockchain: BlockChain,
+    pub storage: Box<dyn ChainStorage>,
     pub series_states: Vec<TradingCardSeriesReleaseState>,
+    pub ledger: CardLedger,
+    pub transactions: TransactionIndex,
+    /// Events of every block appended, for the API's event streams.
+    pub events: broadcast::Sender<ChainEvent>,
 }
 
 impl DeckChain {
-    const BLOCKCHAIN_FILENAME: &'static str = "blockchain.json";
+    const SALTS_DIRNAME: &'static str = "salts";
+    const EVENT_BUFFER: usize = 1024;
 
     pub fn new(config: &Config) -> Result<Self> {
         let blockchain_data_dir = &config.data_dir;
-        let blockchain = DeckChain::get_init_blockchain(blockchain_data_dir)?;
+        let storage_kind = config.storage_kind()?;
+        let (blockchain, storage) = DeckChain::get_init_blockchain(blockchain_data_dir, storage_kind)?;
+        let ledger = CardLedger::from_blockchain(&blockchain)?;
+        let transactions = TransactionIndex::from_blockchain(&blockchain);
         let mut deckchain = DeckChain {
             data_dir: blockchain_data_dir.to_string(),
             blockchain,
+            storage,
             series_states: Vec::new(),
+            ledger,
+            transactions,
+            events: broadcast::channel(DeckChain::EVENT_BUFFER).0,
         };
 
         let releases = deckchain.card_series_releases();
@@ -43,14 +118,99 @@ impl DeckChain {
         Ok(deckchain)
     }
 
-    fn get_init_blockchain(blockchain_data_dir: &str) -> Result<BlockChain> {
-        let path = format!("{}/{}", blockchain_data_dir, DeckChain::BLOCKCHAIN_FILENAME);
-        BlockChain::new(&path, Value::Null)
+    /// Opens the configured storage backend. A data directory that still
+    /// holds only a legacy `blockchain.json` is migrated to the backend once,
+    /// keeping the JSON file as `blockchain.json.migrated`.
+    ///
+    /// A new genesis block is only written when there is no chain at all. An
+    /// invalid chain file is quarantined and loading fails until `repair`
+    /// has recovered it.
+    fn get_init_blockchain(
+        blockchain_data_dir: &str,
+        storage_kind: StorageKind,
+    ) -> Result<(BlockChain, Box<dyn ChainStorage>)> {
+        let storage = storage_kind.open(&storage_kind.path_in(blockchain_data_dir));
+        if storage_kind != StorageKind::Json {
+            DeckChain::migrate_legacy_json(blockchain_data_dir, storage.as_ref())?;
+        }
+
+        let blockchain = BlockChain::new(storage.as_ref(), Value::Null)?;
+        Ok((blockchain, storage))
+    }
+
+    fn migrate_legacy_json(blockchain_data_dir: &str, storage: &dyn ChainStorage) -> Result<()> {
+        let json_path = StorageKind::Json.path_in(blockchain_data_dir);
+        BlockChain::check_not_quarantined(&json_path)?;
+        if !Path::new(&json_path).exists() || Path::new(storage.path()).exists() {
+            return Ok(());
+        }
+
+        let blockchain = BlockChain::load(&JsonFileStorage::new(&json_path))
+            .map_err(|e| BlockChain::quarantine_invalid(&json_path, e))?;
+        tracing::info!("Migrating {} blocks from {} to {}", blockchain.blocks.len(), json_path, storage.path());
+        storage.write_blocks(&blockchain.blocks)?;
+        fs::rename(&json_path, format!("{}.migrated", json_path))?;
+        Ok(())
     }
 
-    pub fn save(&self) -> Result<()> {
-        let full_path = format!("{}/{}", self.data_dir, DeckChain::BLOCKCHAIN_FILENAME);
-        self.blockchain.save(&full_path)
+    /// Rewrites the configured storage from the best chain in the data
+    /// directory, truncated back to its last valid block. The chain is read
+    /// from the quarantined chain file if there is one, else from the live
+    /// one, falling back to a legacy `blockchain.json`. A quarantined or
+    /// truncated source is kept as a timestamped `.corrupt` backup.
+    pub fn repair(config: &Config) -> Result<ChainRepair> {
+        let storage_kind = config.storage_kind()?;
+        let path = storage_kind.path_in(&config.data_dir);
+        let json_path = StorageKind::Json.path_in(&config.data_dir);
+
+        let mut candidates = vec![
+            (BlockChain::quarantine_path(&path), storage_kind),
+            (path.clone(), storage_kind),
+        ];
+        if storage_kind != StorageKind::Json {
+            candidates.push((BlockChain::quarantine_path(&json_path), StorageKind::Json));
+            candidates.push((json_path, StorageKind::Json));
+        }
+        let (source, source_kind) = candidates
+            .into_iter()
+            .find(|(candidate, _)| Path::new(candidate).exists())
+            .ok_or_else(|| DeckForgeError::BlockchainNotFound { path: path.clone() })?;
+
+        let blocks = source_kind.open(&source).recover_blocks()?;
+        let read_blocks = blocks.len();
+        let mut blockchain = BlockChain { blocks };
+        let reason = blockchain.truncate_to_valid().map(|e| e.to_string());
+        if blockchain.blocks.is_empty() {
+            return Err(DeckForgeError::Validation {
+                reason: format!("No valid genesis block found in {}", source),
+            });
+        }
+
+        let quarantined = source.ends_with(".quarantined");
+        let backup = if quarantined || reason.is_some() {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            let backup = format!("{}.corrupt-{}", source.trim_end_matches(".quarantined"), timestamp);
+            fs::copy(&source, &backup)?;
+            Some(backup)
+        } else {
+            None
+        };
+
+        storage_kind.open(&path).write_blocks(&blockchain.blocks)?;
+        if quarantined {
+            fs::remove_file(&source)?;
+        }
+
+        Ok(ChainRepair {
+            source,
+            kept_blocks: blockchain.blocks.len(),
+            discarded_blocks: read_blocks - blockchain.blocks.len(),
+            reason,
+            backup,
+        })
     }
 
     #[allow(dead_code)] // public API
@@ -58,9 +218,121 @@ impl DeckChain {
         self.blockchain.get_blocks()
     }
 
-    #[allow(dead_code)] // public API
+    /// Appends a block to the chain once it is durably stored.
     pub fn add_block(&mut self, transactions: Vec<BlockTransaction>) -> Result<()> {
-        self.blockchain.add_block(transactions)
+        let (block, undo) = self.blockchain.prepare_block(transactions, &mut self.ledger)?;
+        if let Err(e) = self.storage.append_block(&block) {
+            self.ledger.undo(undo);
+            return Err(e);
+        }
+        self.transactions.add_block(&block);
+        let events = ChainEvent::from_block(&block);
+        self.blockchain.blocks.push(block);
+
+        for event in events {
+            // Sending only fails when nobody is subscribed.
+            let _ = self.events.send(event);
+        }
+        Ok(())
+    }
+
+    /// A minted card with its series details and current owner.
+    pub fn card(&self, card_id: &str) -> Result<CardDetails> {
+        let not_found = || DeckForgeError::CardNotFound {
+            card_id: card_id.to_string(),
+        };
+        let ownership = self.ledger.ownership(card_id).ok_or_else(not_found)?;
+        let mint = self.transactions.card_locations(card_id).first().ok_or_else(not_found)?;
+        let (series_id, minted) = match &self.transaction_at(mint).transaction_type {
+            TransactionType::MintCards { series_id, cards, .. } => {
+                let minted = cards.iter().find(|card| card.card_id == card_id).ok_or_else(not_found)?;
+                (series_id, minted)
+            }
+            _ => return Err(not_found()),
+        };
+
+        let card_config = self
+            .series_release_state(series_id)?
+            .series
+            .get_card_configs()
+            .iter()
+            .find(|config| config.number == minted.number)
+            .ok_or_else(|| DeckForgeError::Validation {
+                reason: format!("Series '{}' has no card number {}", series_id, minted.number),
+            })?;
+
+        Ok(CardDetails {
+            card_id: card_id.to_string(),
+            card: TradingCard::from_card_config(
+                card_config,
+                minted.properties.clone(),
+                series_id.clone(),
+                minted.serial.clone(),
+            ),
+            owner: ownership.owner.clone(),
+            transfers: ownership.transfers,
+        })
+    }
+
+    /// Every card held by an address, in card id order.
+    pub fn wallet_cards(&self, address: &str) -> Result<Vec<CardDetails>> {
+        let card_ids = match self.storage.card_index() {
+            Some(index) => index.cards_owned_by(address)?,
+            None => self.ledger.cards_owned_by(address),
+        };
+        card_ids.iter().map(|card_id| self.card(card_id)).collect()
+    }
+
+    /// The mint, pack opening and transfers of a card, oldest first.
+    pub fn card_history(&self, card_id: &str) -> Result<Vec<CardEvent>> {
+        let locations = match self.storage.card_index() {
+            Some(index) => index.card_locations(card_id)?,
+            None => self.transactions.card_locations(card_id).to_vec(),
+        };
+        if locations.is_empty() {
+            return Err(DeckForgeError::CardNotFound {
+                card_id: card_id.to_string(),
+            });
+        }
+
+        let events = locations
+            .iter()
+            .filter_map(|location| {
+                let tx = self.transaction_at(location);
+                let (from, to) = match &tx.transaction_type {
+                    TransactionType::MintCards { owner, .. } => (None, owner),
+                    TransactionType::OpenPack { receiver, .. } => {
+                        (Some(BlockChain::NULL_ADDRESS.to_string()), receiver)
+                    }
+                    TransactionType::TransferCard { sender, receiver, .. } => (Some(sender.clone()), receiver),
+                    _ => return None,
+                };
+                Some(CardEvent {
+                    transaction_id: tx.id(),
+                    transaction_type: tx.transaction_type.name().to_string(),
+                    block_index: location.block_index,
+                    position: location.position,
+                    timestamp: self.blockchain.blocks[location.block_index as usize].timestamp,
+                    from,
+                    to: to.clone(),
+                })
+            })
+            .collect();
+        Ok(events)
+    }
+
+    /// The transaction at an indexed location, which always exists.
+    fn transaction_at(&self, location: &TransactionLocation) -> &BlockTransaction {
+        &self.blockchain.blocks[location.block_index as usize].transactions[location.position]
+    }
+
+    /// A confirmed transaction by id, with its block and confirmation count.
+    pub fn transaction(&self, transaction_id: &str) -> Result<TransactionRecord> {
+        self.transactions
+            .record(&self.blockchain, transaction_id)
+            .ok_or_else(|| DeckForgeError::UnknownTransaction {
+                id: transaction_id.to_string(),
+            })
     }
 
     #[allow(dead_code)] // public API
@@ -82,6 +354,47 @@ impl DeckChain {
             .collect()
     }
 
+    fn series_release_state(&self, series_id: &str) -> Result<&TradingCardSeriesReleaseState> {
+        self.series_states
+            .iter()
+            .find(|state| state.id == series_id)
+            .ok_or_else(|| DeckForgeError::SeriesNotFound {
+                id: series_id.to_string(),
+            })
+    }
+
+    /// Minted and remaining cards of a released series, with its shuffle commitment.
+    pub fn series_state(&self, series_id: &str) -> Result<SeriesState> {
+        let release = self.series_release_state(series_id)?;
+        let shuffle = self
+            .shuffle_commitment(series_id)
+            .map(|(shuffle_hash, salt_commitment)| ShuffleCommitment {
+                shuffle_hash,
+                salt_commitment,
+                revealed: self.revealed_salt(series_id).is_some(),
+            });
+        Ok(SeriesState {
+            series_id: series_id.to_string(),
+            packs_opened: self.ledger.packs_opened(series_id),
+            availability: release.availability(&self.ledger),
+            shuffle,
+        })
+    }
+
+    /// The card configs of a released series, optionally only those of one
+    /// rarity and/or type (case-insensitive).
+    pub fn series_cards(&self, series_id: &str, rarity: Option<u32>, card_type: Option<&str>) -> Result<Vec<CardConfig>> {
+        let release = self.series_release_state(series_id)?;
+        Ok(release
+            .series
+            .get_card_configs()
+            .iter()
+            .filter(|card| rarity.is_none_or(|rarity| card.rarity == rarity))
+            .filter(|card| card_type.is_none_or(|card_type| card.card_type.eq_ignore_ascii_case(card_type)))
+            .cloned()
+            .collect())
+    }
+
     /// Retrieves the data from a specific card series release stored in the blockchain.
     pub fn card_series_release(&self, series_id: &str) -> Result<Value> {
         let all_releases = self.card_series_releases();
@@ -100,28 +413,280 @@ impl DeckChain {
             })
     }
 
-    /// Creates a new card series release in the blockchain.
+    /// Creates a new card series release in the blockchain, minting every
+    /// card of the series to the null address in the same block.
+    ///
+    /// The block also commits to the deck shuffle: the public shuffle hash and
+    /// a digest of the private salt. The salt itself is kept in the data
+    /// directory until it is revealed with `do_reveal_shuffle`.
     pub fn do_release_series(&mut self, series_file: String) -> Result<()> {
         let series_data = read_to_string(&series_file)?;
-        let series_json: Value = serde_json::from_str(&series_data)?;
+        self.release_series_data(&series_data)
+    }
+
+    /// Releases a series from its JSON text, as `do_release_series` does for a file.
+    /// The series must pass every `TradingCardSeries` rule before anything is written.
+    pub fn release_series_data(&mut self, series_data: &str) -> Result<()> {
+        let series_json: Value = serde_json::from_str(series_data)?;
+        let series: TradingCardSeries = serde_json::from_value(series_json.clone())?;
 
+        series.validate_series()?;
         self.validate_series(&series_json)?;
 
         let mut hasher = Sha3_256::new();
         hasher.update(series_data.as_bytes());
         let series_hash = format!("{:x}", hasher.finalize());
 
-        let transaction = BlockTransaction::new(TransactionType::ReleaseSet {
+        let cards = TradingCardSeriesReleaseState::mint_cards(&series);
+
+        let private_salt = TradingCardSeriesReleaseState::generate_private_salt();
+        let series_state = TradingCardSeriesReleaseState::new_from_series(series.clone(), private_salt);
+
+        let release = BlockTransaction::new(TransactionType::ReleaseSet {
             id: series_hash,
             data: series_json,
         });
+        let mint = BlockTransaction::new_mint(
+            series.id.clone(),
+            BlockChain::NULL_ADDRESS.to_string(),
+            &cards,
+        );
+
+        let commit = BlockTransaction::new(TransactionType::CommitShuffle {
+            series_id: series.id.clone(),
+            shuffle_hash: series_state.shuffle_hash.clone(),
+            salt_commitment: TradingCardSeriesReleaseState::salt_commitment(&private_salt),
+        });
+
+        // The salt is stored first so a committed shuffle can always be
+        // revealed, and removed again if the release never reaches the chain.
+        self.write_salt(&series.id, &private_salt)?;
+        if let Err(e) = self.add_block(vec![release, mint, commit]) {
+            let _ = fs::remove_file(self.salt_path(&series.id)?);
+            return Err(e);
+        }
+        self.series_states.push(series_state);
+        tracing::info!("ReleaseSet transaction inserted successfully, {} cards minted.", cards.len());
+        Ok(())
+    }
+
+    /// Publishes the private salt of a released series, allowing anyone to
+    /// verify its shuffle against the commitment made at release.
+    pub fn do_reveal_shuffle(&mut self, series_id: &str) -> Result<()> {
+        let private_salt = self
+            .read_salt(series_id)?
+            .ok_or_else(|| DeckForgeError::Validation {
+                reason: format!("No private salt stored for series '{}'", series_id),
+            })?;
+
+        let transaction = BlockTransaction::new(TransactionType::RevealShuffle {
+            series_id: series_id.to_string(),
+            private_salt: hex::encode(private_salt),
+        });
+
+        self.add_block(vec![transaction])?;
+        tracing::info!("RevealShuffle transaction inserted for series {}.", series_id);
+        Ok(())
+    }
+
+    /// Appends a signed card transfer, rejecting it unless the sender owns the card.
+    pub fn do_transfer(&mut self, transaction: BlockTransaction) -> Result<()> {
+        if !matches!(transaction.transaction_type, TransactionType::TransferCard { .. }) {
+            return Err(DeckForgeError::Validation {
+                reason: "Expected a TransferCard transaction".to_string(),
+            });
+        }
+
+        self.add_block(vec![transaction])?;
+        tracing::info!("TransferCard transaction inserted successfully.");
+        Ok(())
+    }
+
+    /// Opens a pack of a released series, dealing the next undealt cards of
+    /// the committed deck to `receiver`.
+    pub fn do_open_pack(
+        &mut self,
+        series_id: &str,
+        pack_id: &str,
+        receiver: &str,
+    ) -> Result<Vec<TradingCard>> {
+        let series_state = self
+            .series_states
+            .iter()
+            .find(|state| state.id == series_id)
+            .ok_or_else(|| DeckForgeError::SeriesNotFound {
+                id: series_id.to_string(),
+            })?;
+        let pack = series_state.series.get_pack(pack_id).ok_or_else(|| {
+            DeckForgeError::PackNotFound {
+                series_id: series_id.to_string(),
+                pack_id: pack_id.to_string(),
+            }
+        })?;
+        let private_salt = self.shuffle_salt(series_id)?.ok_or_else(|| {
+            DeckForgeError::Validation {
+                reason: format!("Shuffle salt for series '{}' is not available", series_id),
+            }
+        })?;
+
+        let cards = series_state.deal_pack(
+            pack_id,
+            pack,
+            private_salt,
+            self.ledger.packs_opened(series_id),
+            |card| self.ledger.is_undealt(series_id, &card.card_id()),
+        )?;
+
+        let transaction = BlockTransaction::new(TransactionType::OpenPack {
+            series_id: series_id.to_string(),
+            pack_id: pack_id.to_string(),
+            receiver: receiver.to_string(),
+            cards: cards.iter().map(|card| card.card_id()).collect(),
+        });
+
+        self.add_block(vec![transaction])?;
+        tracing::info!("OpenPack transaction inserted: {} {} to {}.", series_id, pack_id, receiver);
+        Ok(cards)
+    }
+
+    /// Regenerates the shuffled deck of a series purely from on-chain data,
+    /// after checking the revealed salt against the release commitment.
+    /// Every pack opened so far is replayed against the regenerated deck.
+    pub fn verify_shuffle(&self, series_id: &str) -> Result<TradingCardSeriesReleaseState> {
+        let (shuffle_hash, commitment) = self.shuffle_commitment(series_id).ok_or_else(|| {
+            DeckForgeError::ShuffleNotCommitted {
+                id: series_id.to_string(),
+            }
+        })?;
+        let private_salt = self
+            .revealed_salt(series_id)
+            .ok_or_else(|| DeckForgeError::Validation {
+                reason: format!("Shuffle salt for series '{}' has not been revealed", series_id),
+            })?;
+        let private_salt = TradingCardSeriesReleaseState::parse_salt(&private_salt)?;
+
+        if TradingCardSeriesReleaseState::salt_commitment(&private_salt) != commitment {
+            return Err(DeckForgeError::ShuffleCommitmentMismatch {
+                id: series_id.to_string(),
+            });
+        }
+
+        let series = TradingCardSeries::from_deckchain(self, series_id.to_string())?;
+        let mut release = TradingCardSeriesReleaseState {
+            id: series.id.clone(),
+            series,
+            released_cards: Vec::new(),
+            shuffle_hash,
+        };
+        release.build_cards(private_salt);
+        self.verify_pack_deals(&release, private_salt)?;
+        Ok(release)
+    }
+
+    fn verify_pack_deals(
+        &self,
+        release: &TradingCardSeriesReleaseState,
+        private_salt: [u8; 16],
+    ) -> Result<()> {
+        let mut dealt: HashSet<String> = HashSet::new();
+        let mut pack_index = 0;
+
+        for tx in self.blockchain.blocks.iter().flat_map(|block| &block.transactions) {
+            if let TransactionType::OpenPack {
+                series_id,
+                pack_id,
+                cards,
+                ..
+            } = &tx.transaction_type
+            {
+                if *series_id != release.id {
+                    continue;
+                }
+                let pack = release.series.get_pack(pack_id).ok_or_else(|| {
+                    DeckForgeError::PackNotFound {
+                        series_id: series_id.clone(),
+                        pack_id: pack_id.clone(),
+                    }
+                })?;
+                let expected: Vec<String> = release
+                    .deal_pack(pack_id, pack, private_salt, pack_index, |card| {
+                        !dealt.contains(&card.card_id())
+                    })?
+                    .iter()
+                    .map(|card| card.card_id())
+                    .collect();
+                if expected != *cards {
+                    return Err(DeckForgeError::Validation {
+                        reason: format!(
+                            "Pack {} of series '{}' does not match the committed deck",
+                            pack_index, series_id
+                        ),
+                    });
+                }
+                dealt.extend(expected);
+                pack_index += 1;
+            }
+        }
 
-        self.blockchain.add_block(vec![transaction])?;
-        self.save()?;
-        tracing::info!("ReleaseSet transaction inserted successfully.");
         Ok(())
     }
 
+    /// The shuffle hash and salt commitment recorded for a series, if any.
+    pub fn shuffle_commitment(&self, series_id: &str) -> Option<(String, String)> {
+        self.ledger.shuffle_commitment(series_id).cloned()
+    }
+
+    /// The salt revealed on chain for a series, if it has been revealed.
+    pub fn revealed_salt(&self, series_id: &str) -> Option<String> {
+        self.ledger.revealed_salt(series_id).map(str::to_string)
+    }
+
+    /// The salt for a series: the on-chain reveal if present, otherwise the
+    /// private copy kept in the data directory.
+    pub fn shuffle_salt(&self, series_id: &str) -> Result<Option<[u8; 16]>> {
+        match self.revealed_salt(series_id) {
+            Some(salt) => Ok(Some(TradingCardSeriesReleaseState::parse_salt(&salt)?)),
+            None => self.read_salt(series_id),
+        }
+    }
+
+    /// The salt file of a series. Ids that could leave the salts directory
+    /// are refused.
+    fn salt_path(&self, series_id: &str) -> Result<String> {
+        if series_id.is_empty() || series_id.contains(['/', '\\']) || series_id.contains("..") {
+            return Err(DeckForgeError::Validation {
+                reason: format!("Series id '{}' must not contain '/', '\\' or '..'", series_id),
+            });
+        }
+        Ok(format!("{}/{}/{}.salt", self.data_dir, DeckChain::SALTS_DIRNAME, series_id))
+    }
+
+    /// Writes the salt durably, via a temporary file, before its commitment
+    /// goes on chain: a committed shuffle whose salt was lost in a crash
+    /// could never deal packs or be revealed.
+    fn write_salt(&self, series_id: &str, private_salt: &[u8; 16]) -> Result<()> {
+        let path = self.salt_path(series_id)?;
+        if let Some(parent) = Path::new(&path).parent() {
+            fs::create_dir_all(parent)?;
+            sync_parent_dir(&parent.to_string_lossy())?;
+        }
+        let tmp_path = format!("{}.tmp", path);
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(hex::encode(private_salt).as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &path)?;
+        sync_parent_dir(&path)
+    }
+
+    fn read_salt(&self, series_id: &str) -> Result<Option<[u8; 16]>> {
+        let path = self.salt_path(series_id)?;
+        if !Path::new(&path).exists() {
+            return Ok(None);
+        }
+        let salt = read_to_string(path)?;
+        Ok(Some(TradingCardSeriesReleaseState::parse_salt(salt.trim())?))
+    }
+
     /// Validates configuration data of a card series.
     pub fn validate_series(&self, series_json: &Value) -> Result<()> {
         let series_id = series_json
@@ -145,6 +710,8 @@ impl DeckChain {
 mod tests {
     use super::*;
     use crate::blockchain::testing::init_test_config;
+    use crate::crypto::keypair::KeyPair;
+    use crate::crypto::wallet::Wallet;
 
     #[test]
     fn test_release_series_not_found() {
@@ -166,6 +733,300 @@ mod tests {
         assert_eq!(series_data.get("id").unwrap().as_str().unwrap(), "LEGACYDECK-1");
     }
 
+    #[test]
+    fn test_release_series_mints_cards() {
+        let (config, _tmp) = init_test_config();
+        let mut deckchain = DeckChain::new(&config).unwrap();
+        deckchain.do_release_series("test/series.json".to_string()).unwrap();
+
+        let series = TradingCardSeries::from_file("test/series.json").unwrap();
+        assert_eq!(deckchain.ledger.card_count() as u32, series.get_mint_total());
+        assert_eq!(
+            deckchain.ledger.owner_of("LEGACYDECK-1-1-001"),
+            Some(BlockChain::NULL_ADDRESS)
+        );
+
+        let reloaded = DeckChain::new(&config).unwrap();
+        assert_eq!(reloaded.ledger.card_count(), deckchain.ledger.card_count());
+    }
+
+    #[test]
+    fn test_migrates_json_chain_to_block_log() {
+        let (config, _tmp) = init_test_config();
+        let genesis = Block::new_genesis(Value::Null);
+        let block1 = Block::new(&genesis, vec![]);
+        let json_chain = BlockChain { blocks: vec![genesis, block1] };
+        let json_path = StorageKind::Json.path_in(&config.data_dir);
+        json_chain.save(&JsonFileStorage::new(&json_path)).unwrap();
+
+        let deckchain = DeckChain::new(&config).unwrap();
+        assert_eq!(deckchain.get_blocks().len(), 2);
+        assert_eq!(deckchain.storage.load_blocks().unwrap().len(), 2);
+        assert!(!Path::new(&json_path).exists());
+        assert!(Path::new(&format!("{}.migrated", json_path)).exists());
+    }
+
+    #[test]
+    fn test_recovers_from_torn_block_append() {
+        let (config, _tmp) = init_test_config();
+        let mut deckchain = DeckChain::new(&config).unwrap();
+        deckchain.add_block(vec![]).unwrap();
+        deckchain.add_block(vec![]).unwrap();
+
+        let log_path = StorageKind::Log.path_in(&config.data_dir);
+        let len = fs::metadata(&log_path).unwrap().len();
+        fs::OpenOptions::new().write(true).open(&log_path).unwrap().set_len(len - 3).unwrap();
+
+        let mut recovered = DeckChain::new(&config).unwrap();
+        assert_eq!(recovered.get_blocks().len(), 2);
+        recovered.add_block(vec![]).unwrap();
+        assert_eq!(DeckChain::new(&config).unwrap().get_blocks().len(), 3);
+    }
+
+    #[test]
+    fn test_tampered_chain_is_quarantined_and_repaired() {
+        let (config, _tmp) = init_test_config();
+        let mut deckchain = DeckChain::new(&config).unwrap();
+        deckchain.add_block(vec![]).unwrap();
+        deckchain.add_block(vec![]).unwrap();
+
+        let log_path = StorageKind::Log.path_in(&config.data_dir);
+        let mut blocks = deckchain.get_blocks().to_vec();
+        blocks[2].timestamp += 1;
+        deckchain.storage.write_blocks(&blocks).unwrap();
+
+        let err = DeckChain::new(&config).err().unwrap();
+        assert!(matches!(err, DeckForgeError::ChainQuarantined { .. }));
+        assert!(!Path::new(&log_path).exists());
+        assert!(DeckChain::new(&config).is_err());
+
+        let repair = DeckChain::repair(&config).unwrap();
+        assert_eq!(repair.kept_blocks, 2);
+        assert_eq!(repair.discarded_blocks, 1);
+        assert!(Path::new(repair.backup.as_ref().unwrap()).exists());
+
+        let repaired = DeckChain::new(&config).unwrap();
+        assert_eq!(repaired.get_blocks().len(), 2);
+        assert_eq!(repaired.get_blocks()[1].hash, deckchain.get_blocks()[1].hash);
+    }
+
+    #[test]
+    fn test_sqlite_storage() {
+        let (mut config, _tmp) = init_test_config();
+        config.storage = Some("sqlite".to_string());
+        let mut deckchain = DeckChain::new(&config).unwrap();
+        deckchain.add_block(vec![]).unwrap();
+
+        assert!(Path::new(&StorageKind::Sqlite.path_in(&config.data_dir)).exists());
+        assert!(!Path::new(&StorageKind::Log.path_in(&config.data_dir)).exists());
+        let reloaded = DeckChain::new(&config).unwrap();
+        assert_eq!(reloaded.get_blocks().len(), 2);
+        assert_eq!(reloaded.get_blocks()[1].hash, deckchain.get_blocks()[1].hash);
+
+        deckchain.do_release_series("test/series.json".to_string()).unwrap();
+        let receiver = "0x8ba82d54332db0c58edc1120a15409aa8cd5f7d9";
+        let dealt = deckchain.do_open_pack("LEGACYDECK-1", "booster", receiver).unwrap();
+        let cards = deckchain.wallet_cards(receiver).unwrap();
+        assert_eq!(cards.len(), dealt.len());
+        let history = deckchain.card_history(&cards[0].card_id).unwrap();
+        let types: Vec<&str> = history.iter().map(|event| event.transaction_type.as_str()).collect();
+        assert_eq!(types, vec!["MintCards", "OpenPack"]);
+    }
+
+    #[test]
+    fn test_reveal_and_verify_shuffle() {
+        let (config, _tmp) = init_test_config();
+        let mut deckchain = DeckChain::new(&config).unwrap();
+        deckchain.do_release_series("test/series.json".to_string()).unwrap();
+        assert!(deckchain.shuffle_commitment("LEGACYDECK-1").is_some());
+        assert!(deckchain.verify_shuffle("LEGACYDECK-1").is_err());
+
+        deckchain.do_reveal_shuffle("LEGACYDECK-1").unwrap();
+        let verified = deckchain.verify_shuffle("LEGACYDECK-1").unwrap();
+
+        let reloaded = DeckChain::new(&config).unwrap();
+        let state = &reloaded.series_states[0];
+        assert_eq!(verified.shuffle_hash, state.shuffle_hash);
+        assert_eq!(verified.released_cards, state.released_cards);
+        assert_eq!(verified.released_cards, deckchain.series_states[0].released_cards);
+    }
+
+    #[test]
+    fn test_open_pack() {
+        let (config, _tmp) = init_test_config();
+        let mut deckchain = DeckChain::new(&config).unwrap();
+        deckchain.do_release_series("test/series.json".to_string()).unwrap();
+
+        let receiver = "0x8ba82d54332db0c58edc1120a15409aa8cd5f7d9";
+        let first = deckchain.do_open_pack("LEGACYDECK-1", "booster", receiver).unwrap();
+        let second = deckchain.do_open_pack("LEGACYDECK-1", "booster", receiver).unwrap();
+        assert_eq!(first.len(), 10);
+        assert_eq!(deckchain.ledger.cards_owned_by(receiver).len(), 20);
+        assert!(first.iter().all(|card| !second.contains(card)));
+
+        assert!(matches!(
+            deckchain.do_open_pack("LEGACYDECK-1", "missing", receiver),
+            Err(DeckForgeError::PackNotFound { .. })
+        ));
+
+        deckchain.do_reveal_shuffle("LEGACYDECK-1").unwrap();
+        assert!(deckchain.verify_shuffle("LEGACYDECK-1").is_ok());
+    }
+
+    #[test]
+    fn test_card_sent_to_null_address_is_not_dealt_again() {
+        let (config, _tmp) = init_test_config();
+        let mut deckchain = DeckChain::new(&config).unwrap();
+        deckchain.do_release_series("test/series.json".to_string()).unwrap();
+
+        let key_pair = KeyPair::new();
+        let owner = Wallet::pub_key_to_wallet_address(&key_pair.public_key_as_string()).unwrap();
+        let first = deckchain.do_open_pack("LEGACYDECK-1", "booster", &owner).unwrap();
+        let card_id = first[0].card_id();
+
+        for receiver in [BlockChain::NULL_ADDRESS, ""] {
+            let transfer = BlockTransaction::new_transfer(&key_pair, card_id.clone(), receiver.to_string(), 0).unwrap();
+            assert!(matches!(
+                deckchain.do_transfer(transfer),
+                Err(DeckForgeError::InvalidReceiver { .. })
+            ));
+        }
+        assert!(matches!(
+            deckchain.do_open_pack("LEGACYDECK-1", "booster", "not-an-address"),
+            Err(DeckForgeError::InvalidReceiver { .. })
+        ));
+
+        let second = deckchain.do_open_pack("LEGACYDECK-1", "booster", &owner).unwrap();
+        assert!(second.iter().all(|card| card.card_id() != card_id));
+        assert_eq!(deckchain.ledger.owner_of(&card_id), Some(owner.as_str()));
+
+        deckchain.do_reveal_shuffle("LEGACYDECK-1").unwrap();
+        assert!(deckchain.verify_shuffle("LEGACYDECK-1").is_ok());
+    }
+
+    #[test]
+    fn test_series_state() {
+        let (config, _tmp) = init_test_config();
+        let mut deckchain = DeckChain::new(&config).unwrap();
+        deckchain.do_release_series("test/series.json".to_string()).unwrap();
+
+        let before = deckchain.series_state("LEGACYDECK-1").unwrap();
+        let minted = deckchain.series_states[0].series.get_mint_total() as u64;
+        assert_eq!(before.availability.total_minted, minted);
+        assert_eq!(before.availability.remaining, minted);
+        assert!(!before.shuffle.as_ref().unwrap().revealed);
+
+        let receiver = "0x8ba82d54332db0c58edc1120a15409aa8cd5f7d9";
+        let dealt = deckchain.do_open_pack("LEGACYDECK-1", "booster", receiver).unwrap();
+        let after = deckchain.series_state("LEGACYDECK-1").unwrap();
+        assert_eq!(after.packs_opened, 1);
+        assert_eq!(after.availability.remaining, minted - dealt.len() as u64);
+        let by_rarity: u64 = after.availability.rarities.iter().map(|r| r.remaining).sum();
+        assert_eq!(by_rarity, after.availability.remaining);
+        for special in &after.availability.specials {
+            assert!(special.remaining <= special.minted);
+        }
+
+        let all = deckchain.series_cards("LEGACYDECK-1", None, None).unwrap();
+        let rarity = all[0].rarity;
+        let filtered = deckchain.series_cards("LEGACYDECK-1", Some(rarity), None).unwrap();
+        assert!(!filtered.is_empty() && filtered.iter().all(|card| card.rarity == rarity));
+        let card_type = all[0].card_type.to_uppercase();
+        let typed = deckchain.series_cards("LEGACYDECK-1", None, Some(&card_type)).unwrap();
+        assert!(typed.iter().all(|card| card.card_type.eq_ignore_ascii_case(&card_type)));
+        assert!(!typed.is_empty());
+        assert!(deckchain.series_state("MISSING").is_err());
+    }
+
+    #[test]
+    fn test_card_details_and_history() {
+        let (config, _tmp) = init_test_config();
+        let mut deckchain = DeckChain::new(&config).unwrap();
+        deckchain.do_release_series("test/series.json".to_string()).unwrap();
+
+        let receiver = "0x8ba82d54332db0c58edc1120a15409aa8cd5f7d9";
+        let dealt = deckchain.do_open_pack("LEGACYDECK-1", "booster", receiver).unwrap();
+        let card_id = dealt[0].card_id();
+
+        let details = deckchain.card(&card_id).unwrap();
+        assert_eq!(details.card, dealt[0]);
+        assert_eq!(details.owner, receiver);
+        assert_eq!(details.transfers, 0);
+
+        let wallet = deckchain.wallet_cards(receiver).unwrap();
+        assert_eq!(wallet.len(), 10);
+        assert!(wallet.iter().any(|card| card.card_id == card_id));
+        assert!(deckchain.wallet_cards("0xnobody").unwrap().is_empty());
+
+        let history = deckchain.card_history(&card_id).unwrap();
+        let types: Vec<&str> = history.iter().map(|event| event.transaction_type.as_str()).collect();
+        assert_eq!(types, vec!["MintCards", "OpenPack"]);
+        assert_eq!(history[0].from, None);
+        assert_eq!(history[1].to, receiver);
+
+        assert!(matches!(
+            deckchain.card("LEGACYDECK-1-9999-001"),
+            Err(DeckForgeError::CardNotFound { .. })
+        ));
+        assert!(deckchain.card_history("LEGACYDECK-1-9999-001").is_err());
+    }
+
+    #[test]
+    fn test_release_invalid_series() {
+        let (config, tmp) = init_test_config();
+        let mut deckchain = DeckChain::new(&config).unwrap();
+
+        let mut series_json: Value =
+            serde_json::from_str(&read_to_string("test/series.json").unwrap()).unwrap();
+        series_json["name"] = Value::from("");
+        series_json["config"]["cards"][0]["rarity"] = Value::from(4);
+        let series_file = format!("{}/invalid.json", tmp.path().to_str().unwrap());
+        fs::write(&series_file, series_json.to_string()).unwrap();
+
+        let err = deckchain.do_release_series(series_file).unwrap_err();
+        match err {
+            DeckForgeError::InvalidSeries { reasons } => {
+                assert!(reasons.contains(&TradingCardSeries::ERROR_NO_NAME.to_string()));
+                assert!(reasons.contains(&TradingCardSeries::ERROR_CARD_RARITIES_MISMATCH.to_string()));
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+        assert_eq!(deckchain.get_blocks().len(), 1);
+        assert!(DeckChain::new(&config).is_ok());
+    }
+
+    #[test]
+    fn test_release_series_id_cannot_escape_salts_dir() {
+        let (config, tmp) = init_test_config();
+        let mut deckchain = DeckChain::new(&config).unwrap();
+
+        let mut series_json: Value =
+            serde_json::from_str(&read_to_string("test/series.json").unwrap()).unwrap();
+        series_json["id"] = Value::from("../../escaped");
+        let series_file = format!("{}/escape.json", tmp.path().to_str().unwrap());
+        fs::write(&series_file, series_json.to_string()).unwrap();
+
+        let err = deckchain.do_release_series(series_file).unwrap_err();
+        assert!(matches!(err, DeckForgeError::Validation { .. }), "{}", err);
+        assert_eq!(deckchain.get_blocks().len(), 1);
+        assert!(!Path::new(&format!("{}/escaped.salt", config.data_dir)).exists());
+        assert!(!tmp.path().join("escaped.salt").exists());
+    }
+
+    #[test]
+    fn test_failed_release_leaves_no_salt() {
+        let (config, _tmp) = init_test_config();
+        let mut deckchain = DeckChain::new(&config).unwrap();
+        let chain_path = deckchain.storage.path().to_string();
+        fs::remove_file(&chain_path).unwrap();
+        fs::create_dir(&chain_path).unwrap();
+
+        assert!(deckchain.do_release_series("test/series.json".to_string()).is_err());
+        assert_eq!(deckchain.get_blocks().len(), 1);
+        assert!(deckchain.shuffle_commitment("LEGACYDECK-1").is_none());
+        assert!(!Path::new(&deckchain.salt_path("LEGACYDECK-1").unwrap()).exists());
+    }
+
     #[test]
     fn test_release_series_twice() {
         let (config, _tmp) = init_test_config();
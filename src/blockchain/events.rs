@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+
+use crate::blockchain::block::Block;
+use crate::blockchain::chain::BlockChain;
+use crate::blockchain::transaction::TransactionType;
+
+/// What a block did to the chain, as pushed to event stream subscribers.
+///
+/// A block's transaction events come first and its `BlockAdded` last, so a
+/// subscriber that has seen `BlockAdded` for block `n` has every event up to
+/// it and can resume from block `n + 1`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "type")]
+pub enum ChainEvent {
+    BlockAdded {
+        block_index: u64,
+        hash: String,
+        timestamp: u128,
+        transactions: usize,
+    },
+    SeriesReleased {
+        block_index: u64,
+        transaction_id: String,
+        series_id: String,
+    },
+    CardMinted {
+        block_index: u64,
+        transaction_id: String,
+        series_id: String,
+        owner: String,
+        cards: Vec<String>,
+    },
+    /// A card changing hands: a transfer, or a pack opening dealing it from
+    /// the null address.
+    CardTransferred {
+        block_index: u64,
+        transaction_id: String,
+        series_id: String,
+        card_id: String,
+        from: String,
+        to: String,
+    },
+}
+
+/// Restricts a stream to the activity of one address and/or series.
+/// `BlockAdded` always passes, so filtered subscribers can still track how
+/// far they have read.
+#[derive(Clone, Default, Deserialize, Debug)]
+pub struct EventFilter {
+    pub address: Option<String>,
+    pub series: Option<String>,
+}
+
+impl ChainEvent {
+    pub fn from_block(block: &Block) -> Vec<ChainEvent> {
+        let mut events = Vec::new();
+        for tx in &block.transactions {
+            let transaction_id = tx.id();
+            match &tx.transaction_type {
+                TransactionType::ReleaseSet { data, .. } => {
+                    if let Some(series_id) = data.get("id").and_then(|v| v.as_str()) {
+                        events.push(ChainEvent::SeriesReleased {
+                            block_index: block.index,
+                            transaction_id,
+                            series_id: series_id.to_string(),
+                        });
+                    }
+                }
+                TransactionType::MintCards {
+                    series_id,
+                    owner,
+                    cards,
+                } => events.push(ChainEvent::CardMinted {
+                    block_index: block.index,
+                    transaction_id,
+                    series_id: series_id.clone(),
+                    owner: owner.clone(),
+                    cards: cards.iter().map(|card| card.card_id.clone()).collect(),
+                }),
+                TransactionType::OpenPack {
+                    series_id,
+                    receiver,
+                    cards,
+                    ..
+                } => events.extend(cards.iter().map(|card_id| ChainEvent::CardTransferred {
+                    block_index: block.index,
+                    transaction_id: transaction_id.clone(),
+                    series_id: series_id.clone(),
+                    card_id: card_id.clone(),
+                    from: BlockChain::NULL_ADDRESS.to_string(),
+                    to: receiver.clone(),
+                })),
+                TransactionType::TransferCard {
+                    card_id,
+                    sender,
+                    receiver,
+                    ..
+                } => events.push(ChainEvent::CardTransferred {
+                    block_index: block.index,
+                    transaction_id,
+                    series_id: ChainEvent::card_series(card_id).to_string(),
+                    card_id: card_id.clone(),
+                    from: sender.clone(),
+                    to: receiver.clone(),
+                }),
+                _ => {}
+            }
+        }
+        events.push(ChainEvent::BlockAdded {
+            block_index: block.index,
+            hash: block.hash.clone(),
+            timestamp: block.timestamp,
+            transactions: block.transactions.len(),
+        });
+        events
+    }
+
+    /// The events of every block from index `from` on, for resuming a stream.
+    pub fn replay(blockchain: &BlockChain, from: u64) -> Vec<ChainEvent> {
+        blockchain
+            .get_blocks_from(from, usize::MAX)
+            .iter()
+            .flat_map(ChainEvent::from_block)
+            .collect()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChainEvent::BlockAdded { .. } => "BlockAdded",
+            ChainEvent::SeriesReleased { .. } => "SeriesReleased",
+            ChainEvent::CardMinted { .. } => "CardMinted",
+            ChainEvent::CardTransferred { .. } => "CardTransferred",
+        }
+    }
+
+    #[allow(dead_code)] // public API
+    pub fn block_index(&self) -> u64 {
+        match self {
+            ChainEvent::BlockAdded { block_index, .. }
+            | ChainEvent::SeriesReleased { block_index, .. }
+            | ChainEvent::CardMinted { block_index, .. }
+            | ChainEvent::CardTransferred { block_index, .. } => *block_index,
+        }
+    }
+
+    pub fn matches(&self, filter: &EventFilter) -> bool {
+        let (series_id, addresses): (&str, Vec<&str>) = match self {
+            ChainEvent::BlockAdded { .. } => return true,
+            ChainEvent::SeriesReleased { series_id, .. } => (series_id, vec![]),
+            ChainEvent::CardMinted { series_id, owner, .. } => (series_id, vec![owner]),
+            ChainEvent::CardTransferred { series_id, from, to, .. } => (series_id, vec![from, to]),
+        };
+        let series_matches = filter.series.as_deref().is_none_or(|series| series == series_id);
+        let address_matches = filter
+            .address
+            .as_deref()
+            .is_none_or(|address| addresses.iter().any(|a| a.eq_ignore_ascii_case(address)));
+        series_matches && address_matches
+    }
+
+    /// The series part of a `{series}-{number}-{serial}` card id. Numbers and
+    /// serials never contain a dash, so this holds for dashed series ids too.
+    fn card_series(card_id: &str) -> &str {
+        card_id.rsplitn(3, '-').nth(2).unwrap_or(card_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::Value;
+
+    use crate::blockchain::transaction::BlockTransaction;
+
+    #[test]
+    fn test_block_events_and_filters() {
+        let genesis = Block::new_genesis(Value::Null);
+        let block = Block::new(
+            &genesis,
+            vec![BlockTransaction::new(TransactionType::OpenPack {
+                series_id: "SERIES-1".to_string(),
+                pack_id: "booster".to_string(),
+                receiver: "0xABC".to_string(),
+                cards: vec!["SERIES-1-1-001".to_string(), "SERIES-1-2-001".to_string()],
+            })],
+        );
+
+        let events = ChainEvent::from_block(&block);
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[2].name(), "BlockAdded");
+        assert!(events.iter().all(|event| event.block_index() == 1));
+
+        let wallet = EventFilter {
+            address: Some("0xabc".to_string()),
+            series: None,
+        };
+        let other_series = EventFilter {
+            address: None,
+            series: Some("SERIES-2".to_string()),
+        };
+        assert!(events.iter().all(|event| event.matches(&wallet)));
+        assert!(!events[0].matches(&other_series));
+        assert!(events[2].matches(&other_series));
+
+        let json = serde_json::to_value(&events[0]).unwrap();
+        assert_eq!(json["type"], "CardTransferred");
+        assert_eq!(json["from"], BlockChain::NULL_ADDRESS);
+    }
+
+    #[test]
+    fn test_card_series() {
+        assert_eq!(ChainEvent::card_series("LEGACYDECK-1-12-003"), "LEGACYDECK-1");
+        assert_eq!(ChainEvent::card_series("S-1-001"), "S");
+    }
+}
@@ -0,0 +1,618 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::blockchain::chain::BlockChain;
+use crate::blockchain::transaction::{BlockTransaction, MintedCard, TransactionType};
+use crate::card::series::TradingCardSeries;
+use crate::card::seriesreleasestate::TradingCardSeriesReleaseState;
+use crate::crypto::wallet::Wallet;
+use crate::error::{DeckForgeError, Result};
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct CardOwnership {
+    pub owner: String,
+    pub transfers: u64,
+}
+
+/// In-memory index of card ownership, built by replaying the chain.
+#[derive(Clone, Serialize, Deserialize, Default)]
+pub struct CardLedger {
+    cards: HashMap<String, CardOwnership>,
+    inventories: HashMap<String, BTreeSet<String>>,
+    /// Definitions of the released series, by id.
+    released_series: HashMap<String, TradingCardSeries>,
+    /// Shuffle hash and salt commitment per series.
+    shuffle_commitments: HashMap<String, (String, String)>,
+    /// Revealed salt per series, as hex.
+    revealed_shuffles: HashMap<String, String>,
+    packs_opened: HashMap<String, u64>,
+    /// Cards dealt into packs, per series. A dealt card never returns to the
+    /// pool, whoever holds it later.
+    dealt: HashMap<String, HashSet<String>>,
+    /// Undo log of the block being applied, if any.
+    #[serde(skip)]
+    journal: Option<LedgerUndo>,
+}
+
+/// What applying a block changed, enough to restore the ledger as it was
+/// before the block.
+#[derive(Clone, Default, Debug)]
+pub struct LedgerUndo {
+    cards: HashMap<String, Option<CardOwnership>>,
+    released_series: Vec<String>,
+    shuffle_commitments: Vec<String>,
+    revealed_shuffles: Vec<String>,
+    packs_opened: HashMap<String, Option<u64>>,
+    dealt: Vec<(String, String)>,
+}
+
+impl CardLedger {
+    pub fn new() -> Self {
+        CardLedger::default()
+    }
+
+    /// Replays every block of the chain, checking each transaction as it is applied.
+    pub fn from_blockchain(blockchain: &BlockChain) -> Result<Self> {
+        let mut ledger = CardLedger::new();
+        for block in &blockchain.blocks {
+            for tx in &block.transactions {
+                ledger.apply_transaction(tx)?;
+            }
+        }
+        Ok(ledger)
+    }
+
+    /// Applies every transaction of a block, returning how to undo them. On
+    /// error the ledger is left unchanged.
+    pub fn apply_block(&mut self, transactions: &[BlockTransaction]) -> Result<LedgerUndo> {
+        self.journal = Some(LedgerUndo::default());
+        let result = transactions.iter().try_for_each(|tx| self.apply_transaction(tx));
+        let undo = self.journal.take().unwrap_or_default();
+        match result {
+            Ok(()) => Ok(undo),
+            Err(e) => {
+                self.undo(undo);
+                Err(e)
+            }
+        }
+    }
+
+    /// Reverts a block applied by `apply_block`. Blocks must be undone
+    /// newest first.
+    pub fn undo(&mut self, undo: LedgerUndo) {
+        for (card_id, previous) in undo.cards {
+            if let Some(current) = self.cards.remove(&card_id) {
+                self.remove_from_inventory(&current.owner, &card_id);
+            }
+            if let Some(previous) = previous {
+                self.set_owner(&card_id, &previous.owner, previous.transfers);
+            }
+        }
+        for series_id in undo.released_series {
+            self.released_series.remove(&series_id);
+        }
+        for series_id in undo.shuffle_commitments {
+            self.shuffle_commitments.remove(&series_id);
+        }
+        for series_id in undo.revealed_shuffles {
+            self.revealed_shuffles.remove(&series_id);
+        }
+        for (series_id, previous) in undo.packs_opened {
+            match previous {
+                Some(count) => self.packs_opened.insert(series_id, count),
+                None => self.packs_opened.remove(&series_id),
+            };
+        }
+        for (series_id, card_id) in undo.dealt {
+            if let Some(dealt) = self.dealt.get_mut(&series_id) {
+                dealt.remove(&card_id);
+                if dealt.is_empty() {
+                    self.dealt.remove(&series_id);
+                }
+            }
+        }
+    }
+
+    /// Checks a transaction against the current ownership and applies it.
+    /// On error the ledger is left unchanged.
+    pub fn apply_transaction(&mut self, tx: &BlockTransaction) -> Result<()> {
+        tx.verify_signature()?;
+
+        match &tx.transaction_type {
+            TransactionType::ReleaseSet { data, .. } => {
+                let series: TradingCardSeries =
+                    serde_json::from_value(data.clone()).map_err(|e| DeckForgeError::Validation {
+                        reason: format!("Released data is not a series: {}", e),
+                    })?;
+                series.validate_series()?;
+                if self.released_series.contains_key(&series.id) {
+                    return Err(DeckForgeError::AlreadyReleased { id: series.id });
+                }
+                if let Some(journal) = &mut self.journal {
+                    journal.released_series.push(series.id.clone());
+                }
+                self.released_series.insert(series.id.clone(), series);
+            }
+            TransactionType::MintCards {
+                series_id,
+                owner,
+                cards,
+            } => {
+                let series = self.released_series.get(series_id).ok_or_else(|| {
+                    DeckForgeError::MintUnreleasedSeries {
+                        id: series_id.clone(),
+                    }
+                })?;
+                if owner != BlockChain::NULL_ADDRESS {
+                    return Err(DeckForgeError::Validation {
+                        reason: format!(
+                            "Cards of series '{}' must be minted to {}, not {}",
+                            series_id,
+                            BlockChain::NULL_ADDRESS,
+                            owner
+                        ),
+                    });
+                }
+
+                let numbers: HashSet<u32> = series.get_card_configs().iter().map(|c| c.number).collect();
+                let mint_list = series.get_mint_list();
+                let mut batch = HashSet::with_capacity(cards.len());
+                for card in cards {
+                    let expected_id = format!("{}-{}-{}", series_id, card.number, card.serial);
+                    if card.card_id != expected_id {
+                        return Err(DeckForgeError::Validation {
+                            reason: format!(
+                                "Minted card id '{}' does not match '{}'",
+                                card.card_id, expected_id
+                            ),
+                        });
+                    }
+                    if !numbers.contains(&card.number) || !CardLedger::is_minted_serial(card, &mint_list) {
+                        return Err(DeckForgeError::Validation {
+                            reason: format!(
+                                "Minted card '{}' is not part of series '{}'",
+                                card.card_id, series_id
+                            ),
+                        });
+                    }
+                    if self.cards.contains_key(&card.card_id) || !batch.insert(&card.card_id) {
+                        return Err(DeckForgeError::AlreadyMinted {
+                            card_id: card.card_id.clone(),
+                        });
+                    }
+                }
+
+                for card in cards {
+                    self.set_owner(&card.card_id, owner, 0);
+                }
+            }
+            TransactionType::CommitShuffle {
+                series_id,
+                shuffle_hash,
+                salt_commitment,
+            } => {
+                if !self.released_series.contains_key(series_id)
+                    || self.shuffle_commitments.contains_key(series_id)
+                {
+                    return Err(DeckForgeError::Validation {
+                        reason: format!(
+                            "Shuffle commitment for series '{}' must follow its release exactly once",
+                            series_id
+                        ),
+                    });
+                }
+                self.shuffle_commitments
+                    .insert(series_id.clone(), (shuffle_hash.clone(), salt_commitment.clone()));
+                if let Some(journal) = &mut self.journal {
+                    journal.shuffle_commitments.push(series_id.clone());
+                }
+            }
+            TransactionType::RevealShuffle {
+                series_id,
+                private_salt,
+            } => {
+                let (_, commitment) = self.shuffle_commitments.get(series_id).ok_or_else(|| {
+                    DeckForgeError::ShuffleNotCommitted {
+                        id: series_id.clone(),
+                    }
+                })?;
+                let salt = TradingCardSeriesReleaseState::parse_salt(private_salt)?;
+                if self.revealed_shuffles.contains_key(series_id)
+                    || TradingCardSeriesReleaseState::salt_commitment(&salt) != *commitment
+                {
+                    return Err(DeckForgeError::ShuffleCommitmentMismatch {
+                        id: series_id.clone(),
+                    });
+                }
+                self.revealed_shuffles.insert(series_id.clone(), private_salt.clone());
+                if let Some(journal) = &mut self.journal {
+                    journal.revealed_shuffles.push(series_id.clone());
+                }
+            }
+            TransactionType::OpenPack {
+                series_id,
+                receiver,
+                cards,
+                ..
+            } => {
+                CardLedger::check_receiver(receiver)?;
+                let prefix = format!("{}-", series_id);
+                let mut batch = HashSet::with_capacity(cards.len());
+                for card_id in cards {
+                    if !card_id.starts_with(&prefix)
+                        || !self.is_undealt(series_id, card_id)
+                        || !batch.insert(card_id)
+                    {
+                        return Err(DeckForgeError::NotCardOwner {
+                            card_id: card_id.clone(),
+                            address: BlockChain::NULL_ADDRESS.to_string(),
+                        });
+                    }
+                }
+
+                for card_id in cards {
+                    let transfers = self.cards[card_id].transfers;
+                    self.set_owner(card_id, receiver, transfers);
+                }
+                let dealt = self.dealt.entry(series_id.clone()).or_default();
+                for card_id in cards {
+                    if dealt.insert(card_id.clone()) {
+                        if let Some(journal) = &mut self.journal {
+                            journal.dealt.push((series_id.clone(), card_id.clone()));
+                        }
+                    }
+                }
+                let opened = self.packs_opened.entry(series_id.clone()).or_insert(0);
+                if let Some(journal) = &mut self.journal {
+                    journal
+                        .packs_opened
+                        .entry(series_id.clone())
+                        .or_insert(Some(*opened).filter(|count| *count > 0));
+                }
+                *opened += 1;
+            }
+            TransactionType::TransferCard {
+                card_id,
+                sender,
+                receiver,
+                nonce,
+                ..
+            } => {
+                CardLedger::check_receiver(receiver)?;
+                let transfers = match self.cards.get(card_id) {
+                    Some(ownership) if ownership.owner == *sender => ownership.transfers,
+                    _ => {
+                        return Err(DeckForgeError::NotCardOwner {
+                            card_id: card_id.clone(),
+                            address: sender.clone(),
+                        })
+                    }
+                };
+                if *nonce != transfers {
+                    return Err(DeckForgeError::InvalidNonce {
+                        card_id: card_id.clone(),
+                        expected: transfers,
+                        got: *nonce,
+                    });
+                }
+                self.set_owner(card_id, receiver, transfers + 1);
+            }
+            TransactionType::Init { .. } => {}
+        }
+
+        Ok(())
+    }
+
+    /// Whether a minted card's serial and special finishes are those the
+    /// series' mint list gives that serial.
+    fn is_minted_serial(card: &MintedCard, mint_list: &[Vec<String>]) -> bool {
+        let Ok(serial) = card.serial.parse::<u32>() else {
+            return false;
+        };
+        let canonical = TradingCardSeriesReleaseState::format_serial(serial, mint_list.len() as u32);
+        canonical == card.serial
+            && (serial as usize)
+                .checked_sub(1)
+                .and_then(|index| mint_list.get(index))
+                .is_some_and(|properties| *properties == card.properties)
+    }
+
+    /// Cards may only be dealt or transferred to a real wallet address. The
+    /// null address holds the undealt pool and must not receive cards.
+    fn check_receiver(receiver: &str) -> Result<()> {
+        if receiver == BlockChain::NULL_ADDRESS || !Wallet::is_valid_address(receiver) {
+            return Err(DeckForgeError::InvalidReceiver {
+                address: receiver.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn set_owner(&mut self, card_id: &str, owner: &str, transfers: u64) {
+        if let Some(journal) = &mut self.journal {
+            journal
+                .cards
+                .entry(card_id.to_string())
+                .or_insert_with(|| self.cards.get(card_id).cloned());
+        }
+        if let Some(previous) = self.cards.get(card_id) {
+            let previous_owner = previous.owner.clone();
+            self.remove_from_inventory(&previous_owner, card_id);
+        }
+
+        self.inventories
+            .entry(owner.to_string())
+            .or_default()
+            .insert(card_id.to_string());
+        self.cards.insert(
+            card_id.to_string(),
+            CardOwnership {
+                owner: owner.to_string(),
+                transfers,
+            },
+        );
+    }
+
+    fn remove_from_inventory(&mut self, owner: &str, card_id: &str) {
+        if let Some(inventory) = self.inventories.get_mut(owner) {
+            inventory.remove(card_id);
+            if inventory.is_empty() {
+                self.inventories.remove(owner);
+            }
+        }
+    }
+
+    pub fn owner_of(&self, card_id: &str) -> Option<&str> {
+        self.cards.get(card_id).map(|c| c.owner.as_str())
+    }
+
+    pub fn ownership(&self, card_id: &str) -> Option<&CardOwnership> {
+        self.cards.get(card_id)
+    }
+
+    /// Card ids held by an address, in sorted order.
+    pub fn cards_owned_by(&self, address: &str) -> Vec<String> {
+        self.inventories
+            .get(address)
+            .map(|cards| cards.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Whether a minted card is still in the pool of its series: held by the
+    /// null address and never dealt into a pack.
+    pub fn is_undealt(&self, series_id: &str, card_id: &str) -> bool {
+        self.owner_of(card_id) == Some(BlockChain::NULL_ADDRESS)
+            && !self.dealt.get(series_id).is_some_and(|dealt| dealt.contains(card_id))
+    }
+
+    /// The shuffle hash and salt commitment recorded for a series, if any.
+    pub fn shuffle_commitment(&self, series_id: &str) -> Option<&(String, String)> {
+        self.shuffle_commitments.get(series_id)
+    }
+
+    /// The salt revealed on chain for a series, if it has been revealed.
+    pub fn revealed_salt(&self, series_id: &str) -> Option<&str> {
+        self.revealed_shuffles.get(series_id).map(String::as_str)
+    }
+
+    /// Number of packs opened so far for a series.
+    pub fn packs_opened(&self, series_id: &str) -> u64 {
+        self.packs_opened.get(series_id).copied().unwrap_or(0)
+    }
+
+    #[allow(dead_code)] // public API
+    pub fn card_count(&self) -> usize {
+        self.cards.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::crypto::keypair::KeyPair;
+
+    const CARD_ID: &str = "LEGACYDECK-1-1-001";
+
+    fn release_tx() -> BlockTransaction {
+        BlockTransaction::new(TransactionType::ReleaseSet {
+            id: "hash".to_string(),
+            data: crate::card::series::tests::test_series_json(),
+        })
+    }
+
+    fn owned_ledger(key_pair: &KeyPair) -> (CardLedger, String) {
+        let address = Wallet::pub_key_to_wallet_address(&key_pair.public_key_as_string()).unwrap();
+        let mut ledger = CardLedger::new();
+        ledger.set_owner(CARD_ID, &address, 0);
+        (ledger, address)
+    }
+
+    #[test]
+    fn test_empty_chain_has_no_cards() {
+        let genesis = crate::blockchain::block::Block::new_genesis(serde_json::Value::Null);
+        let chain = BlockChain { blocks: vec![genesis] };
+        let ledger = CardLedger::from_blockchain(&chain).unwrap();
+        assert_eq!(ledger.card_count(), 0);
+        assert!(ledger.owner_of(CARD_ID).is_none());
+    }
+
+    #[test]
+    fn test_transfer_moves_inventory() {
+        let key_pair = KeyPair::new();
+        let (mut ledger, sender) = owned_ledger(&key_pair);
+        let receiver = "0x8ba82d54332db0c58edc1120a15409aa8cd5f7d9".to_string();
+
+        let tx = BlockTransaction::new_transfer(&key_pair, CARD_ID.to_string(), receiver.clone(), 0)
+            .unwrap();
+        ledger.apply_transaction(&tx).unwrap();
+
+        assert_eq!(ledger.owner_of(CARD_ID), Some(receiver.as_str()));
+        assert_eq!(ledger.cards_owned_by(&receiver), vec![CARD_ID.to_string()]);
+        assert!(ledger.cards_owned_by(&sender).is_empty());
+        assert_eq!(ledger.ownership(CARD_ID).unwrap().transfers, 1);
+    }
+
+    #[test]
+    fn test_failed_block_is_rolled_back() {
+        let key_pair = KeyPair::new();
+        let (mut ledger, sender) = owned_ledger(&key_pair);
+        let receiver = "0x8ba82d54332db0c58edc1120a15409aa8cd5f7d9".to_string();
+        let transfer = BlockTransaction::new_transfer(&key_pair, CARD_ID.to_string(), receiver.clone(), 0)
+            .unwrap();
+
+        let err = ledger
+            .apply_block(&[release_tx(), transfer.clone(), transfer.clone()])
+            .unwrap_err();
+        assert!(matches!(err, DeckForgeError::NotCardOwner { .. }));
+        assert_eq!(ledger.owner_of(CARD_ID), Some(sender.as_str()));
+        assert_eq!(ledger.cards_owned_by(&sender), vec![CARD_ID.to_string()]);
+        assert!(ledger.cards_owned_by(&receiver).is_empty());
+        assert_eq!(ledger.ownership(CARD_ID).unwrap().transfers, 0);
+        assert!(ledger.released_series.is_empty());
+
+        let undo = ledger.apply_block(&[release_tx(), transfer]).unwrap();
+        assert_eq!(ledger.owner_of(CARD_ID), Some(receiver.as_str()));
+        ledger.undo(undo);
+        assert_eq!(ledger.owner_of(CARD_ID), Some(sender.as_str()));
+        assert!(ledger.cards_owned_by(&receiver).is_empty());
+        assert!(ledger.released_series.is_empty());
+    }
+
+    #[test]
+    fn test_mint_assigns_owner() {
+        let series = crate::card::series::tests::test_series_data();
+        let cards = TradingCardSeriesReleaseState::mint_cards(&series);
+        let mut ledger = CardLedger::new();
+        ledger.apply_transaction(&release_tx()).unwrap();
+
+        let mint = BlockTransaction::new_mint(
+            series.id.clone(),
+            BlockChain::NULL_ADDRESS.to_string(),
+            &cards,
+        );
+        ledger.apply_transaction(&mint).unwrap();
+
+        assert_eq!(ledger.card_count() as u32, series.get_mint_total());
+        assert_eq!(ledger.owner_of(&cards[0].card_id()), Some(BlockChain::NULL_ADDRESS));
+
+        let err = ledger.apply_transaction(&mint).unwrap_err();
+        assert!(matches!(err, DeckForgeError::AlreadyMinted { .. }));
+    }
+
+    #[test]
+    fn test_mint_must_match_released_series() {
+        let series = crate::card::series::tests::test_series_data();
+        let cards = TradingCardSeriesReleaseState::mint_cards(&series);
+        let mut ledger = CardLedger::new();
+        ledger.apply_transaction(&release_tx()).unwrap();
+
+        let err = ledger.apply_transaction(&release_tx()).unwrap_err();
+        assert!(matches!(err, DeckForgeError::AlreadyReleased { .. }));
+
+        let mint = |owner: &str, card: MintedCard| {
+            BlockTransaction::new(TransactionType::MintCards {
+                series_id: series.id.clone(),
+                owner: owner.to_string(),
+                cards: vec![card],
+            })
+        };
+        let with_id = |mut card: MintedCard| {
+            card.card_id = format!("{}-{}-{}", series.id, card.number, card.serial);
+            card
+        };
+        let valid = MintedCard::from(&cards[0]);
+
+        let receiver = "0x8ba82d54332db0c58edc1120a15409aa8cd5f7d9";
+        assert!(ledger.apply_transaction(&mint(receiver, valid.clone())).is_err());
+
+        let forged = [
+            with_id(MintedCard { number: 9999, ..valid.clone() }),
+            with_id(MintedCard { serial: "99999".to_string(), ..valid.clone() }),
+            with_id(MintedCard { serial: format!("0{}", valid.serial), ..valid.clone() }),
+            MintedCard { properties: vec!["Forged".to_string()], ..valid.clone() },
+        ];
+        for card in forged {
+            let err = ledger.apply_transaction(&mint(BlockChain::NULL_ADDRESS, card)).unwrap_err();
+            assert!(matches!(err, DeckForgeError::Validation { .. }), "{}", err);
+        }
+        assert_eq!(ledger.card_count(), 0);
+
+        ledger.apply_transaction(&mint(BlockChain::NULL_ADDRESS, valid)).unwrap();
+        assert_eq!(ledger.card_count(), 1);
+    }
+
+    #[test]
+    fn test_mint_unreleased_series_rejected() {
+        let series = crate::card::series::tests::test_series_data();
+        let cards = TradingCardSeriesReleaseState::mint_cards(&series);
+        let mut ledger = CardLedger::new();
+
+        let mint = BlockTransaction::new_mint(
+            series.id.clone(),
+            BlockChain::NULL_ADDRESS.to_string(),
+            &cards,
+        );
+        let err = ledger.apply_transaction(&mint).unwrap_err();
+        assert!(matches!(err, DeckForgeError::MintUnreleasedSeries { .. }));
+        assert_eq!(ledger.card_count(), 0);
+    }
+
+    #[test]
+    fn test_reveal_must_match_commitment() {
+        let mut ledger = CardLedger::new();
+        ledger.apply_transaction(&release_tx()).unwrap();
+
+        let salt = [9u8; 16];
+        ledger
+            .apply_transaction(&BlockTransaction::new(TransactionType::CommitShuffle {
+                series_id: "LEGACYDECK-1".to_string(),
+                shuffle_hash: hex::encode([1u8; 16]),
+                salt_commitment: TradingCardSeriesReleaseState::salt_commitment(&salt),
+            }))
+            .unwrap();
+
+        let wrong = BlockTransaction::new(TransactionType::RevealShuffle {
+            series_id: "LEGACYDECK-1".to_string(),
+            private_salt: hex::encode([8u8; 16]),
+        });
+        let err = ledger.apply_transaction(&wrong).unwrap_err();
+        assert!(matches!(err, DeckForgeError::ShuffleCommitmentMismatch { .. }));
+
+        let reveal = BlockTransaction::new(TransactionType::RevealShuffle {
+            series_id: "LEGACYDECK-1".to_string(),
+            private_salt: hex::encode(salt),
+        });
+        ledger.apply_transaction(&reveal).unwrap();
+        assert!(ledger.apply_transaction(&reveal).is_err());
+    }
+
+    #[test]
+    fn test_replayed_transfer_rejected() {
+        let key_pair = KeyPair::new();
+        let (mut ledger, sender) = owned_ledger(&key_pair);
+
+        let tx = BlockTransaction::new_transfer(&key_pair, CARD_ID.to_string(), sender.clone(), 0)
+            .unwrap();
+        ledger.apply_transaction(&tx).unwrap();
+
+        let err = ledger.apply_transaction(&tx).unwrap_err();
+        assert!(matches!(err, DeckForgeError::InvalidNonce { expected: 1, got: 0, .. }));
+        assert_eq!(ledger.owner_of(CARD_ID), Some(sender.as_str()));
+    }
+
+    #[test]
+    fn test_invalid_receivers_rejected() {
+        let key_pair = KeyPair::new();
+        let (mut ledger, sender) = owned_ledger(&key_pair);
+
+        for receiver in [BlockChain::NULL_ADDRESS, "", "0xABC", "0x8BA82D54332DB0C58EDC1120A15409AA8CD5F7D9"] {
+            let tx = BlockTransaction::new_transfer(&key_pair, CARD_ID.to_string(), receiver.to_string(), 0)
+                .unwrap();
+            let err = ledger.apply_transaction(&tx).unwrap_err();
+            assert!(matches!(err, DeckForgeError::InvalidReceiver { .. }), "{}", receiver);
+        }
+        assert_eq!(ledger.owner_of(CARD_ID), Some(sender.as_str()));
+    }
+}
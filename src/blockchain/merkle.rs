@@ -0,0 +1,158 @@
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+
+/// Which side of the running hash a proof sibling sits on.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// One level of a Merkle inclusion proof: the sibling hash to combine with.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ProofStep {
+    pub side: Side,
+    pub hash: String,
+}
+
+/// A Sha3-256 Merkle tree over transaction hashes.
+///
+/// Leaves are the transaction ids (Sha3-256 of the transaction JSON), hashed
+/// into the tree as `Sha3(0x00 || id)`. Inner nodes are
+/// `Sha3(0x01 || left || right)`; the distinct domain bytes keep an inner
+/// node from ever being read as a leaf. An unpaired last node is carried up
+/// to the next level unchanged rather than duplicated. The root of an empty
+/// tree is `Sha3("")`.
+pub struct MerkleTree;
+
+impl MerkleTree {
+    pub fn root(leaves: &[[u8; 32]]) -> [u8; 32] {
+        if leaves.is_empty() {
+            return Sha3_256::digest([]).into();
+        }
+
+        let mut level = MerkleTree::leaf_level(leaves);
+        while level.len() > 1 {
+            level = MerkleTree::next_level(&level);
+        }
+        level[0]
+    }
+
+    /// The sibling hashes proving that leaf `index` is in the tree, from the
+    /// leaf level up.
+    pub fn proof(leaves: &[[u8; 32]], index: usize) -> Option<Vec<ProofStep>> {
+        if index >= leaves.len() {
+            return None;
+        }
+
+        let mut steps = Vec::new();
+        let mut level = MerkleTree::leaf_level(leaves);
+        let mut position = index;
+        while level.len() > 1 {
+            let sibling = position ^ 1;
+            if sibling < level.len() {
+                steps.push(ProofStep {
+                    side: if sibling < position { Side::Left } else { Side::Right },
+                    hash: hex::encode(level[sibling]),
+                });
+            }
+            level = MerkleTree::next_level(&level);
+            position /= 2;
+        }
+        Some(steps)
+    }
+
+    /// Folds a leaf up through its proof and compares the result with `root`.
+    #[allow(dead_code)] // public API
+    pub fn verify(leaf: [u8; 32], steps: &[ProofStep], root: [u8; 32]) -> bool {
+        let mut hash = MerkleTree::hash_leaf(&leaf);
+        for step in steps {
+            let sibling: [u8; 32] = match hex::decode(&step.hash).ok().and_then(|h| h.try_into().ok()) {
+                Some(sibling) => sibling,
+                None => return false,
+            };
+            hash = match step.side {
+                Side::Left => MerkleTree::hash_pair(&sibling, &hash),
+                Side::Right => MerkleTree::hash_pair(&hash, &sibling),
+            };
+        }
+        hash == root
+    }
+
+    fn leaf_level(leaves: &[[u8; 32]]) -> Vec<[u8; 32]> {
+        leaves.iter().map(MerkleTree::hash_leaf).collect()
+    }
+
+    fn next_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+        level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => MerkleTree::hash_pair(left, right),
+                _ => pair[0],
+            })
+            .collect()
+    }
+
+    fn hash_leaf(leaf: &[u8; 32]) -> [u8; 32] {
+        let mut hasher = Sha3_256::new();
+        hasher.update([0x00]);
+        hasher.update(leaf);
+        hasher.finalize().into()
+    }
+
+    fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        let mut hasher = Sha3_256::new();
+        hasher.update([0x01]);
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(count: u8) -> Vec<[u8; 32]> {
+        (0..count).map(|i| Sha3_256::digest([i]).into()).collect()
+    }
+
+    #[test]
+    fn test_every_leaf_proves_inclusion() {
+        for count in 1..=9 {
+            let leaves = leaves(count);
+            let root = MerkleTree::root(&leaves);
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = MerkleTree::proof(&leaves, index).unwrap();
+                assert!(MerkleTree::verify(*leaf, &proof, root), "leaf {} of {}", index, count);
+            }
+        }
+    }
+
+    #[test]
+    fn test_wrong_leaf_or_root_fails() {
+        let leaves = leaves(5);
+        let root = MerkleTree::root(&leaves);
+        let proof = MerkleTree::proof(&leaves, 2).unwrap();
+        assert!(!MerkleTree::verify(leaves[3], &proof, root));
+        assert!(!MerkleTree::verify(leaves[2], &proof, MerkleTree::root(&leaves[..4])));
+        assert!(MerkleTree::proof(&leaves, 5).is_none());
+    }
+
+    #[test]
+    fn test_single_leaf_root_is_hashed_leaf() {
+        let leaves = leaves(1);
+        assert_eq!(MerkleTree::root(&leaves), MerkleTree::hash_leaf(&leaves[0]));
+        assert!(MerkleTree::proof(&leaves, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_inner_node_is_not_a_leaf() {
+        let leaves = leaves(4);
+        let root = MerkleTree::root(&leaves);
+        let inner = MerkleTree::hash_pair(&MerkleTree::hash_leaf(&leaves[0]), &MerkleTree::hash_leaf(&leaves[1]));
+        let upper = MerkleTree::proof(&leaves, 0).unwrap().split_off(1);
+        assert!(!MerkleTree::verify(inner, &upper, root));
+    }
+}
@@ -1,7 +1,15 @@
 pub mod block;
+pub mod blocklog;
+pub mod canonical;
 pub mod chain;
 pub mod deckchain;
+pub mod events;
+pub mod ledger;
+pub mod merkle;
+pub mod sqlite;
+pub mod storage;
 pub mod transaction;
+pub mod txindex;
 
 #[cfg(test)]
 pub mod testing;
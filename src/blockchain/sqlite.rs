@@ -0,0 +1,305 @@
+use std::path::Path;
+
+use rusqlite::{params, Connection, Transaction};
+
+use crate::blockchain::block::Block;
+use crate::blockchain::storage::{CardIndex, ChainStorage};
+use crate::blockchain::transaction::TransactionType;
+use crate::blockchain::txindex::TransactionLocation;
+use crate::error::Result;
+
+/// The chain in an embedded SQLite database.
+///
+/// Blocks are stored whole, as JSON, and the database also keeps indexed
+/// tables of transactions, card movements, current card ownership and
+/// released series, which answer the `CardIndex` lookups without loading the
+/// chain. A connection is opened per call, which keeps the backend `Sync`.
+pub struct SqliteStorage {
+    path: String,
+}
+
+impl SqliteStorage {
+    const SCHEMA: &'static str = "
+        CREATE TABLE IF NOT EXISTS blocks (
+            block_index INTEGER PRIMARY KEY,
+            hash TEXT NOT NULL UNIQUE,
+            previous_hash TEXT NOT NULL,
+            block TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS transactions (
+            block_index INTEGER NOT NULL REFERENCES blocks(block_index),
+            position INTEGER NOT NULL,
+            transaction_id TEXT NOT NULL,
+            transaction_type TEXT NOT NULL,
+            PRIMARY KEY (block_index, position)
+        );
+        CREATE INDEX IF NOT EXISTS transactions_by_id ON transactions(transaction_id);
+        CREATE TABLE IF NOT EXISTS card_movements (
+            card_id TEXT NOT NULL,
+            block_index INTEGER NOT NULL,
+            position INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS card_movements_by_card ON card_movements(card_id, block_index, position);
+        CREATE TABLE IF NOT EXISTS card_owners (
+            card_id TEXT PRIMARY KEY,
+            owner TEXT NOT NULL,
+            transfers INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS card_owners_by_owner ON card_owners(owner);
+        CREATE TABLE IF NOT EXISTS series (
+            series_id TEXT PRIMARY KEY,
+            release_hash TEXT NOT NULL,
+            block_index INTEGER NOT NULL,
+            data TEXT NOT NULL
+        );
+    ";
+
+    pub fn new(path: &str) -> Self {
+        SqliteStorage { path: path.to_string() }
+    }
+
+    fn connect(&self) -> Result<Connection> {
+        let connection = Connection::open(&self.path)?;
+        connection.pragma_update(None, "synchronous", "FULL")?;
+        connection.execute_batch(SqliteStorage::SCHEMA)?;
+        Ok(connection)
+    }
+
+    fn read_blocks(&self, lenient: bool) -> Result<Vec<Block>> {
+        if !Path::new(&self.path).exists() {
+            return Ok(Vec::new());
+        }
+
+        let connection = self.connect()?;
+        let mut statement = connection.prepare("SELECT block FROM blocks ORDER BY block_index")?;
+        let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+
+        let mut blocks = Vec::new();
+        for row in rows {
+            let parsed = row
+                .map_err(Into::into)
+                .and_then(|json| serde_json::from_str::<Block>(&json).map_err(Into::into));
+            match parsed {
+                Ok(block) => blocks.push(block),
+                Err(_) if lenient => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(blocks)
+    }
+
+    /// Inserts a block and updates the index tables for its transactions.
+    /// Transactions are assumed to have been checked by the ledger already.
+    fn insert_block(tx: &Transaction, block: &Block) -> Result<()> {
+        tx.execute(
+            "INSERT INTO blocks (block_index, hash, previous_hash, block) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                block.index as i64,
+                block.hash,
+                block.previous_hash,
+                serde_json::to_string(block)?
+            ],
+        )?;
+
+        for (position, transaction) in block.transactions.iter().enumerate() {
+            let transaction_type = &transaction.transaction_type;
+            tx.execute(
+                "INSERT INTO transactions (block_index, position, transaction_id, transaction_type)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![block.index as i64, position as i64, transaction.id(), transaction_type.name()],
+            )?;
+
+            let moved: Vec<&str> = match transaction_type {
+                TransactionType::MintCards { cards, .. } => cards.iter().map(|card| card.card_id.as_str()).collect(),
+                TransactionType::OpenPack { cards, .. } => cards.iter().map(String::as_str).collect(),
+                TransactionType::TransferCard { card_id, .. } => vec![card_id.as_str()],
+                _ => Vec::new(),
+            };
+            let mut movement = tx.prepare_cached(
+                "INSERT INTO card_movements (card_id, block_index, position) VALUES (?1, ?2, ?3)",
+            )?;
+            for card_id in moved {
+                movement.execute(params![card_id, block.index as i64, position as i64])?;
+            }
+
+            match transaction_type {
+                TransactionType::ReleaseSet { id, data } => {
+                    if let Some(series_id) = data.get("id").and_then(|v| v.as_str()) {
+                        tx.execute(
+                            "INSERT OR REPLACE INTO series (series_id, release_hash, block_index, data)
+                             VALUES (?1, ?2, ?3, ?4)",
+                            params![series_id, id, block.index as i64, data.to_string()],
+                        )?;
+                    }
+                }
+                TransactionType::MintCards { owner, cards, .. } => {
+                    let mut insert = tx.prepare_cached(
+                        "INSERT INTO card_owners (card_id, owner, transfers) VALUES (?1, ?2, 0)",
+                    )?;
+                    for card in cards {
+                        insert.execute(params![card.card_id, owner])?;
+                    }
+                }
+                TransactionType::OpenPack { receiver, cards, .. } => {
+                    let mut update =
+                        tx.prepare_cached("UPDATE card_owners SET owner = ?2 WHERE card_id = ?1")?;
+                    for card_id in cards {
+                        update.execute(params![card_id, receiver])?;
+                    }
+                }
+                TransactionType::TransferCard { card_id, receiver, .. } => {
+                    tx.execute(
+                        "UPDATE card_owners SET owner = ?2, transfers = transfers + 1 WHERE card_id = ?1",
+                        params![card_id, receiver],
+                    )?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CardIndex for SqliteStorage {
+    fn cards_owned_by(&self, owner: &str) -> Result<Vec<String>> {
+        let connection = self.connect()?;
+        let mut statement =
+            connection.prepare("SELECT card_id FROM card_owners WHERE owner = ?1 ORDER BY card_id")?;
+        let cards = statement
+            .query_map([owner], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(cards)
+    }
+
+    fn card_locations(&self, card_id: &str) -> Result<Vec<TransactionLocation>> {
+        let connection = self.connect()?;
+        let mut statement = connection.prepare(
+            "SELECT block_index, position FROM card_movements
+             WHERE card_id = ?1 ORDER BY block_index, position",
+        )?;
+        let locations = statement
+            .query_map([card_id], |row| {
+                Ok(TransactionLocation {
+                    block_index: row.get::<_, i64>(0)? as u64,
+                    position: row.get::<_, i64>(1)? as usize,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(locations)
+    }
+}
+
+impl ChainStorage for SqliteStorage {
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn card_index(&self) -> Option<&dyn CardIndex> {
+        Some(self)
+    }
+
+    fn load_blocks(&self) -> Result<Vec<Block>> {
+        self.read_blocks(false)
+    }
+
+    fn recover_blocks(&self) -> Result<Vec<Block>> {
+        self.read_blocks(true)
+    }
+
+    fn write_blocks(&self, blocks: &[Block]) -> Result<()> {
+        let mut connection = self.connect()?;
+        let tx = connection.transaction()?;
+        tx.execute_batch(
+            "DELETE FROM series; DELETE FROM card_owners; DELETE FROM card_movements;
+             DELETE FROM transactions; DELETE FROM blocks;",
+        )?;
+        for block in blocks {
+            SqliteStorage::insert_block(&tx, block)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn append_block(&self, block: &Block) -> Result<()> {
+        let mut connection = self.connect()?;
+        let tx = connection.transaction()?;
+        SqliteStorage::insert_block(&tx, block)?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::Value;
+    use tempfile::TempDir;
+
+    use crate::blockchain::chain::BlockChain;
+    use crate::blockchain::transaction::BlockTransaction;
+    use crate::card::seriesreleasestate::TradingCardSeriesReleaseState;
+    use crate::crypto::keypair::KeyPair;
+    use crate::crypto::wallet::Wallet;
+
+    #[test]
+    fn test_indexes_ownership_and_transfers() {
+        let tmp_dir = TempDir::new().unwrap();
+        let storage = SqliteStorage::new(&format!("{}/blockchain.sqlite", tmp_dir.path().to_str().unwrap()));
+        assert!(storage.load_blocks().unwrap().is_empty());
+
+        let series = crate::card::series::tests::test_series_data();
+        let cards = TradingCardSeriesReleaseState::mint_cards(&series);
+        let card_id = cards[0].card_id();
+        let key_pair = KeyPair::new();
+        let owner = Wallet::pub_key_to_wallet_address(&key_pair.public_key_as_string()).unwrap();
+
+        let genesis = Block::new_genesis(Value::Null);
+        let release = Block::new(
+            &genesis,
+            vec![
+                BlockTransaction::new(TransactionType::ReleaseSet {
+                    id: "hash".to_string(),
+                    data: crate::card::series::tests::test_series_json(),
+                }),
+                BlockTransaction::new_mint(series.id.clone(), BlockChain::NULL_ADDRESS.to_string(), &cards[..3]),
+            ],
+        );
+        let open = Block::new(
+            &release,
+            vec![BlockTransaction::new(TransactionType::OpenPack {
+                series_id: series.id.clone(),
+                pack_id: "booster".to_string(),
+                receiver: owner.clone(),
+                cards: vec![card_id.clone()],
+            })],
+        );
+        let transfer = Block::new(
+            &open,
+            vec![BlockTransaction::new_transfer(&key_pair, card_id.clone(), owner.clone(), 0).unwrap()],
+        );
+
+        storage.write_blocks(&[genesis, release]).unwrap();
+        storage.append_block(&open).unwrap();
+        storage.append_block(&transfer).unwrap();
+
+        assert_eq!(storage.load_blocks().unwrap().len(), 4);
+        assert_eq!(storage.cards_owned_by(&owner).unwrap(), vec![card_id.clone()]);
+        assert_eq!(storage.cards_owned_by(BlockChain::NULL_ADDRESS).unwrap().len(), 2);
+
+        let blocks: Vec<u64> = storage
+            .card_locations(&card_id)
+            .unwrap()
+            .iter()
+            .map(|location| location.block_index)
+            .collect();
+        assert_eq!(blocks, vec![1, 2, 3]);
+        assert_eq!(storage.card_locations(&cards[1].card_id()).unwrap().len(), 1);
+
+        let connection = storage.connect().unwrap();
+        let series_id: String = connection
+            .query_row("SELECT series_id FROM series WHERE release_hash = ?1", ["hash"], |row| row.get(0))
+            .unwrap();
+        assert_eq!(series_id, series.id);
+    }
+}
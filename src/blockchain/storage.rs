@@ -0,0 +1,265 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::blockchain::block::Block;
+use crate::blockchain::blocklog::BlockLog;
+use crate::blockchain::chain::BlockChain;
+use crate::blockchain::sqlite::SqliteStorage;
+use crate::blockchain::txindex::TransactionLocation;
+use crate::error::{DeckForgeError, Result};
+
+/// Persistence for the blocks of a chain. Backends only store blocks; every
+/// check of their contents is left to `BlockChain::validate`.
+pub trait ChainStorage: Send + Sync {
+    /// The file the chain is stored in.
+    fn path(&self) -> &str;
+
+    /// Loads every stored block, or nothing when no chain has been stored.
+    /// Fails when the stored data itself cannot be read back.
+    fn load_blocks(&self) -> Result<Vec<Block>>;
+
+    /// Reads as many leading blocks as can still be parsed, for repairs.
+    fn recover_blocks(&self) -> Result<Vec<Block>>;
+
+    /// Replaces everything stored with `blocks`.
+    fn write_blocks(&self, blocks: &[Block]) -> Result<()>;
+
+    /// Durably stores one more block after the stored ones.
+    fn append_block(&self, block: &Block) -> Result<()>;
+
+    /// The backend's own card indexes, when it keeps any. Without them card
+    /// lookups are answered from the in-memory ledger.
+    fn card_index(&self) -> Option<&dyn CardIndex> {
+        None
+    }
+}
+
+/// Card lookups a backend can answer from its own indexes.
+pub trait CardIndex {
+    /// Card ids held by an address, in sorted order.
+    fn cards_owned_by(&self, owner: &str) -> Result<Vec<String>>;
+
+    /// Where every mint, pack deal and transfer of a card was confirmed,
+    /// oldest first.
+    fn card_locations(&self, card_id: &str) -> Result<Vec<TransactionLocation>>;
+}
+
+/// The storage backends a data directory can use, selected with `storage`
+/// in the config.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StorageKind {
+    /// The whole chain as one pretty-printed `blockchain.json`, rewritten on
+    /// every block. Simple to inspect; fine for small deployments.
+    Json,
+    /// The append-only, checksummed `blockchain.log`.
+    Log,
+    /// An indexed SQLite database, `blockchain.sqlite`.
+    Sqlite,
+}
+
+impl StorageKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StorageKind::Json => "json",
+            StorageKind::Log => "log",
+            StorageKind::Sqlite => "sqlite",
+        }
+    }
+
+    pub fn filename(&self) -> &'static str {
+        match self {
+            StorageKind::Json => "blockchain.json",
+            StorageKind::Log => "blockchain.log",
+            StorageKind::Sqlite => "blockchain.sqlite",
+        }
+    }
+
+    /// Path of this backend's chain file inside a data directory.
+    pub fn path_in(&self, data_dir: &str) -> String {
+        format!("{}/{}", data_dir, self.filename())
+    }
+
+    /// The backend for the chain file at `path`. Nothing is read or created.
+    pub fn open(&self, path: &str) -> Box<dyn ChainStorage> {
+        match self {
+            StorageKind::Json => Box::new(JsonFileStorage::new(path)),
+            StorageKind::Log => Box::new(BlockLog::new(path)),
+            StorageKind::Sqlite => Box::new(SqliteStorage::new(path)),
+        }
+    }
+}
+
+impl FromStr for StorageKind {
+    type Err = DeckForgeError;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "json" => Ok(StorageKind::Json),
+            "log" => Ok(StorageKind::Log),
+            "sqlite" => Ok(StorageKind::Sqlite),
+            _ => Err(DeckForgeError::UnknownStorage {
+                name: name.to_string(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for StorageKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The chain as a single JSON document. Each append rewrites the file, via a
+/// temporary file so a crash leaves either the old or the new chain.
+pub struct JsonFileStorage {
+    path: String,
+}
+
+impl JsonFileStorage {
+    pub fn new(path: &str) -> Self {
+        JsonFileStorage { path: path.to_string() }
+    }
+}
+
+impl ChainStorage for JsonFileStorage {
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn load_blocks(&self) -> Result<Vec<Block>> {
+        if !Path::new(&self.path).exists() {
+            return Ok(Vec::new());
+        }
+        let contents = fs::read_to_string(&self.path)?;
+        let blockchain: BlockChain = serde_json::from_str(&contents)?;
+        Ok(blockchain.blocks)
+    }
+
+    /// Parses the blocks one by one, keeping every block up to the first
+    /// that cannot be parsed.
+    fn recover_blocks(&self) -> Result<Vec<Block>> {
+        let contents = fs::read_to_string(&self.path)?;
+        let value: serde_json::Value = serde_json::from_str(&contents)?;
+        let blocks = value
+            .get("blocks")
+            .and_then(|blocks| blocks.as_array())
+            .map(|blocks| {
+                blocks
+                    .iter()
+                    .map_while(|block| serde_json::from_value(block.clone()).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(blocks)
+    }
+
+    fn write_blocks(&self, blocks: &[Block]) -> Result<()> {
+        let blockchain = BlockChain {
+            blocks: blocks.to_vec(),
+        };
+        let contents = serde_json::to_string_pretty(&blockchain)?;
+
+        let tmp_path = format!("{}.tmp", self.path);
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+        sync_parent_dir(&self.path)
+    }
+
+    fn append_block(&self, block: &Block) -> Result<()> {
+        let mut blocks = self.load_blocks()?;
+        blocks.push(block.clone());
+        self.write_blocks(&blocks)
+    }
+}
+
+/// Copies the chain of a data directory from one backend to another. The
+/// source chain must be valid, and the target must not hold a chain yet.
+/// Returns the number of blocks copied.
+pub fn migrate(data_dir: &str, from: StorageKind, to: StorageKind) -> Result<usize> {
+    if from == to {
+        return Err(DeckForgeError::Validation {
+            reason: format!("The chain is already stored as {}", to),
+        });
+    }
+
+    let source = from.open(&from.path_in(data_dir));
+    let blockchain = BlockChain::load(source.as_ref())?;
+
+    let target = to.open(&to.path_in(data_dir));
+    if !target.load_blocks()?.is_empty() {
+        return Err(DeckForgeError::Validation {
+            reason: format!("{} already holds a chain; move it away first", target.path()),
+        });
+    }
+    target.write_blocks(&blockchain.blocks)?;
+
+    let copied = BlockChain::load(target.as_ref())?;
+    if copied.blocks.last().map(|b| &b.hash) != blockchain.blocks.last().map(|b| &b.hash) {
+        return Err(DeckForgeError::Validation {
+            reason: format!("{} does not match {} after copying", target.path(), source.path()),
+        });
+    }
+    Ok(copied.blocks.len())
+}
+
+/// Flushes a directory entry change, such as a rename, to disk.
+pub(crate) fn sync_parent_dir(path: &str) -> Result<()> {
+    if let Some(parent) = Path::new(path).parent().filter(|p| !p.as_os_str().is_empty()) {
+        File::open(parent)?.sync_all()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::Value;
+    use tempfile::TempDir;
+
+    fn test_blocks() -> Vec<Block> {
+        let genesis = Block::new_genesis(Value::Null);
+        let block1 = Block::new(&genesis, vec![]);
+        vec![genesis, block1]
+    }
+
+    #[test]
+    fn test_json_storage_round_trip() {
+        let tmp_dir = TempDir::new().unwrap();
+        let storage = JsonFileStorage::new(&StorageKind::Json.path_in(tmp_dir.path().to_str().unwrap()));
+        assert!(storage.load_blocks().unwrap().is_empty());
+
+        let blocks = test_blocks();
+        storage.write_blocks(&blocks[..1]).unwrap();
+        storage.append_block(&blocks[1]).unwrap();
+        assert_eq!(storage.load_blocks().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_migrate_between_backends() {
+        let tmp_dir = TempDir::new().unwrap();
+        let data_dir = tmp_dir.path().to_str().unwrap();
+        let blocks = test_blocks();
+        StorageKind::Log.open(&StorageKind::Log.path_in(data_dir)).write_blocks(&blocks).unwrap();
+
+        assert_eq!(migrate(data_dir, StorageKind::Log, StorageKind::Sqlite).unwrap(), 2);
+        assert_eq!(migrate(data_dir, StorageKind::Sqlite, StorageKind::Json).unwrap(), 2);
+        assert!(migrate(data_dir, StorageKind::Log, StorageKind::Json).is_err());
+        assert!(migrate(data_dir, StorageKind::Log, StorageKind::Log).is_err());
+
+        let json = StorageKind::Json.open(&StorageKind::Json.path_in(data_dir));
+        assert_eq!(json.load_blocks().unwrap()[1].hash, blocks[1].hash);
+    }
+
+    #[test]
+    fn test_storage_kind_from_str() {
+        assert_eq!("sqlite".parse::<StorageKind>().unwrap(), StorageKind::Sqlite);
+        assert!("mysql".parse::<StorageKind>().is_err());
+    }
+}
@@ -15,6 +15,7 @@ pub fn init_test_config() -> (Config, TempDir) {
         data_dir: data_dir_path,
         listen_addr: None,
         authorized_keys_path: None,
+        storage: None,
     };
 
     (config, tmp_dir)
@@ -1,11 +1,76 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use sha3::{Digest, Keccak256, Sha3_256};
+
+use crate::blockchain::canonical::to_canonical_json;
+use crate::card::card::TradingCard;
+use crate::crypto::keypair::KeyPair;
+use crate::crypto::wallet::Wallet;
+use crate::error::{DeckForgeError, Result};
 
 #[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
 pub enum TransactionType {
     Init { data: Value },
     ReleaseSet { id: String, data: Value },
-    TransferCard { card_id: String, sender: String, receiver: String },
+    MintCards {
+        series_id: String,
+        owner: String,
+        cards: Vec<MintedCard>,
+    },
+    CommitShuffle {
+        series_id: String,
+        shuffle_hash: String,
+        salt_commitment: String,
+    },
+    RevealShuffle { series_id: String, private_salt: String },
+    OpenPack {
+        series_id: String,
+        pack_id: String,
+        receiver: String,
+        cards: Vec<String>,
+    },
+    TransferCard {
+        card_id: String,
+        sender: String,
+        receiver: String,
+        nonce: u64,
+        signature: String,
+    },
+}
+
+impl TransactionType {
+    /// The variant name, as it appears in the serialized transaction.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransactionType::Init { .. } => "Init",
+            TransactionType::ReleaseSet { .. } => "ReleaseSet",
+            TransactionType::MintCards { .. } => "MintCards",
+            TransactionType::CommitShuffle { .. } => "CommitShuffle",
+            TransactionType::RevealShuffle { .. } => "RevealShuffle",
+            TransactionType::OpenPack { .. } => "OpenPack",
+            TransactionType::TransferCard { .. } => "TransferCard",
+        }
+    }
+}
+
+/// The on-chain record of a single minted card.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct MintedCard {
+    pub card_id: String,
+    pub number: u32,
+    pub serial: String,
+    pub properties: Vec<String>,
+}
+
+impl From<&TradingCard> for MintedCard {
+    fn from(card: &TradingCard) -> Self {
+        MintedCard {
+            card_id: card.card_id(),
+            number: card.number(),
+            serial: card.serial().to_string(),
+            properties: card.properties.clone(),
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -17,4 +82,113 @@ impl BlockTransaction {
     pub fn new(transaction_type: TransactionType) -> Self {
         BlockTransaction { transaction_type }
     }
+
+    /// Content hash identifying the transaction: hex Sha3-256 of its
+    /// canonical JSON.
+    pub fn id(&self) -> String {
+        hex::encode(self.digest())
+    }
+
+    /// Sha3-256 of the canonical JSON of the transaction; its Merkle leaf in
+    /// current blocks.
+    pub fn digest(&self) -> [u8; 32] {
+        let json = to_canonical_json(self).expect("transactions must be serializable");
+        Sha3_256::digest(json.as_bytes()).into()
+    }
+
+    /// Creates a MintCards transaction recording every given card with its initial owner.
+    pub fn new_mint(series_id: String, owner: String, cards: &[TradingCard]) -> Self {
+        BlockTransaction::new(TransactionType::MintCards {
+            series_id,
+            owner,
+            cards: cards.iter().map(MintedCard::from).collect(),
+        })
+    }
+
+    /// Creates a TransferCard transaction signed by the sender's key pair.
+    ///
+    /// The sender address is derived from the key pair, and `nonce` must be
+    /// the number of TransferCard transactions the card has already seen on the chain.
+    #[allow(dead_code)] // public API
+    pub fn new_transfer(
+        key_pair: &KeyPair,
+        card_id: String,
+        receiver: String,
+        nonce: u64,
+    ) -> Result<Self> {
+        let sender = Wallet::pub_key_to_wallet_address(&key_pair.public_key_as_string())?;
+        let digest = BlockTransaction::transfer_digest(&card_id, &sender, &receiver, nonce);
+        let signature = key_pair.sign_recoverable(digest);
+
+        Ok(BlockTransaction::new(TransactionType::TransferCard {
+            card_id,
+            sender,
+            receiver,
+            nonce,
+            signature,
+        }))
+    }
+
+    /// The digest a sender signs to authorize a card transfer.
+    pub fn transfer_digest(card_id: &str, sender: &str, receiver: &str, nonce: u64) -> [u8; 32] {
+        let payload = format!("deckforge:transfer:{}:{}:{}:{}", card_id, sender, receiver, nonce);
+        Keccak256::digest(payload.as_bytes()).into()
+    }
+
+    /// Checks that a signed transaction was signed by its claimed sender.
+    /// Unsigned transaction types always pass.
+    pub fn verify_signature(&self) -> Result<()> {
+        match &self.transaction_type {
+            TransactionType::TransferCard {
+                card_id,
+                sender,
+                receiver,
+                nonce,
+                signature,
+            } => {
+                let digest = BlockTransaction::transfer_digest(card_id, sender, receiver, *nonce);
+                match Wallet::recover_address(digest, signature) {
+                    Ok(address) if address == *sender => Ok(()),
+                    _ => Err(DeckForgeError::InvalidSignature {
+                        card_id: card_id.clone(),
+                    }),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_transfer_verifies() {
+        let key_pair = KeyPair::new();
+        let tx = BlockTransaction::new_transfer(
+            &key_pair,
+            "LEGACYDECK-1-1-001".to_string(),
+            "0x8ba82d54332db0c58edc1120a15409aa8cd5f7d9".to_string(),
+            0,
+        )
+        .unwrap();
+        assert!(tx.verify_signature().is_ok());
+    }
+
+    #[test]
+    fn test_tampered_transfer_fails() {
+        let key_pair = KeyPair::new();
+        let mut tx = BlockTransaction::new_transfer(
+            &key_pair,
+            "LEGACYDECK-1-1-001".to_string(),
+            "0x8ba82d54332db0c58edc1120a15409aa8cd5f7d9".to_string(),
+            0,
+        )
+        .unwrap();
+        if let TransactionType::TransferCard { receiver, .. } = &mut tx.transaction_type {
+            *receiver = crate::blockchain::chain::BlockChain::NULL_ADDRESS.to_string();
+        }
+        assert!(tx.verify_signature().is_err());
+    }
 }
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::blockchain::block::Block;
+use crate::blockchain::chain::BlockChain;
+use crate::blockchain::transaction::{BlockTransaction, TransactionType};
+
+/// Where a transaction was confirmed.
+#[derive(Clone, Copy, Serialize, Debug, PartialEq)]
+pub struct TransactionLocation {
+    pub block_index: u64,
+    pub position: usize,
+}
+
+/// A transaction looked up by id, with where and when it was confirmed.
+/// `confirmations` counts its own block, so the tip block has one.
+#[derive(Clone, Serialize, Debug)]
+pub struct TransactionRecord {
+    pub transaction_id: String,
+    pub block_index: u64,
+    pub position: usize,
+    pub block_hash: String,
+    pub timestamp: u128,
+    pub confirmations: u64,
+    pub transaction: BlockTransaction,
+}
+
+/// In-memory index from transaction id to location, and from card id to
+/// the transactions that minted, dealt or transferred the card. Built when
+/// the chain loads and extended with every appended block. Identical
+/// transactions share an id; the index keeps the first.
+#[derive(Clone, Default)]
+pub struct TransactionIndex {
+    locations: HashMap<String, TransactionLocation>,
+    cards: HashMap<String, Vec<TransactionLocation>>,
+}
+
+impl TransactionIndex {
+    pub fn from_blockchain(blockchain: &BlockChain) -> Self {
+        let mut index = TransactionIndex::default();
+        for block in &blockchain.blocks {
+            index.add_block(block);
+        }
+        index
+    }
+
+    pub fn add_block(&mut self, block: &Block) {
+        for (position, tx) in block.transactions.iter().enumerate() {
+            let location = TransactionLocation {
+                block_index: block.index,
+                position,
+            };
+            self.locations.entry(tx.id()).or_insert(location);
+
+            let card_ids: Vec<&str> = match &tx.transaction_type {
+                TransactionType::MintCards { cards, .. } => cards.iter().map(|card| card.card_id.as_str()).collect(),
+                TransactionType::OpenPack { cards, .. } => cards.iter().map(String::as_str).collect(),
+                TransactionType::TransferCard { card_id, .. } => vec![card_id.as_str()],
+                _ => Vec::new(),
+            };
+            for card_id in card_ids {
+                self.cards.entry(card_id.to_string()).or_default().push(location);
+            }
+        }
+    }
+
+    /// The transactions touching a card, oldest first. The first is its mint.
+    pub fn card_locations(&self, card_id: &str) -> &[TransactionLocation] {
+        self.cards.get(card_id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn get(&self, transaction_id: &str) -> Option<TransactionLocation> {
+        self.locations.get(&transaction_id.to_ascii_lowercase()).copied()
+    }
+
+    /// The indexed transaction with its block details.
+    pub fn record(&self, blockchain: &BlockChain, transaction_id: &str) -> Option<TransactionRecord> {
+        let location = self.get(transaction_id)?;
+        let block = blockchain.get_block(location.block_index)?;
+        let transaction = block.transactions.get(location.position)?;
+        Some(TransactionRecord {
+            transaction_id: transaction.id(),
+            block_index: block.index,
+            position: location.position,
+            block_hash: block.hash.clone(),
+            timestamp: block.timestamp,
+            confirmations: blockchain.blocks.len() as u64 - block.index,
+            transaction: transaction.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::Value;
+
+    use crate::blockchain::transaction::TransactionType;
+
+    #[test]
+    fn test_indexes_every_transaction() {
+        let genesis = Block::new_genesis(Value::Null);
+        let reveal = BlockTransaction::new(TransactionType::RevealShuffle {
+            series_id: "SERIES".to_string(),
+            private_salt: String::new(),
+        });
+        let block1 = Block::new(&genesis, vec![reveal.clone()]);
+        let mut blockchain = BlockChain {
+            blocks: vec![genesis, block1],
+        };
+
+        let mut index = TransactionIndex::from_blockchain(&blockchain);
+        assert_eq!(index.locations.len(), 2);
+        let record = index.record(&blockchain, &reveal.id().to_uppercase()).unwrap();
+        assert_eq!(record.block_index, 1);
+        assert_eq!(record.position, 0);
+        assert_eq!(record.confirmations, 1);
+
+        let block2 = Block::new(&blockchain.blocks[1], vec![]);
+        index.add_block(&block2);
+        blockchain.blocks.push(block2);
+        assert_eq!(index.record(&blockchain, &reveal.id()).unwrap().confirmations, 2);
+        assert!(index.record(&blockchain, "00").is_none());
+    }
+
+    #[test]
+    fn test_indexes_card_transactions() {
+        let key_pair = crate::crypto::keypair::KeyPair::new();
+        let genesis = Block::new_genesis(Value::Null);
+        let open = Block::new(
+            &genesis,
+            vec![BlockTransaction::new(TransactionType::OpenPack {
+                series_id: "S".to_string(),
+                pack_id: "booster".to_string(),
+                receiver: "0xabc".to_string(),
+                cards: vec!["S-1-001".to_string(), "S-2-001".to_string()],
+            })],
+        );
+        let transfer = Block::new(
+            &open,
+            vec![BlockTransaction::new_transfer(&key_pair, "S-1-001".to_string(), "0xdef".to_string(), 0).unwrap()],
+        );
+        let blockchain = BlockChain {
+            blocks: vec![genesis, open, transfer],
+        };
+
+        let index = TransactionIndex::from_blockchain(&blockchain);
+        let blocks: Vec<u64> = index.card_locations("S-1-001").iter().map(|l| l.block_index).collect();
+        assert_eq!(blocks, vec![1, 2]);
+        assert_eq!(index.card_locations("S-2-001").len(), 1);
+        assert!(index.card_locations("S-3-001").is_empty());
+    }
+}
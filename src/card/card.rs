@@ -34,4 +34,30 @@ impl TradingCard {
             properties,
         }
     }
+
+    /// Stable identifier of a minted card: `{series}-{number}-{serial}`.
+    pub fn card_id(&self) -> String {
+        format!("{}-{}-{}", self.series, self.number, self.serial)
+    }
+
+    pub fn series(&self) -> &str {
+        &self.series
+    }
+
+    pub fn number(&self) -> u32 {
+        self.number
+    }
+
+    pub fn serial(&self) -> &str {
+        &self.serial
+    }
+
+    pub fn rarity(&self) -> u32 {
+        self.rarity
+    }
+
+    /// Whether the card was minted with a special finish (borderless, foil, ...).
+    pub fn is_special(&self) -> bool {
+        self.properties.iter().any(|p| !p.is_empty())
+    }
 }
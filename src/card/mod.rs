@@ -1,3 +1,5 @@
+#[allow(clippy::module_inception)]
 pub mod card;
 pub mod series;
 pub mod seriesreleasestate;
+pub mod simulation;
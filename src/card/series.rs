@@ -1,8 +1,9 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::BufReader;
 
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 
 use crate::blockchain::deckchain::DeckChain;
 use crate::error::{DeckForgeError, Result};
@@ -19,6 +20,8 @@ pub struct TradingCardSeries {
 pub struct Config {
     distribution: Distribution,
     cards: Vec<CardConfig>,
+    #[serde(default)]
+    packs: HashMap<String, PackConfig>,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -46,6 +49,38 @@ pub struct Special {
     numbered_sets: u32,
 }
 
+/// A pack product: how many cards it holds, the minimum number of cards of
+/// each rarity, and the chance that one card has a special finish.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct PackConfig {
+    pub name: String,
+    pub cards: u32,
+    #[serde(default)]
+    pub rarity_slots: HashMap<String, u32>,
+    #[serde(default)]
+    pub special_odds: f64,
+}
+
+#[allow(dead_code)]
+impl PackConfig {
+    /// Guaranteed rarity slots as (rarity, count), highest rarity first.
+    pub fn rarity_slots_as_sorted_vec(&self) -> Vec<(u32, u32)> {
+        let mut vec: Vec<(u32, u32)> = self
+            .rarity_slots
+            .iter()
+            .filter_map(|(key, count)| key.parse::<u32>().ok().map(|k| (k, *count)))
+            .collect();
+        vec.sort_by_key(|a| std::cmp::Reverse(a.0));
+        vec
+    }
+
+    /// Total guaranteed rarity slots, summed wide so that no file can
+    /// overflow it.
+    pub fn guaranteed_cards(&self) -> u64 {
+        self.rarity_slots.values().map(|&count| count as u64).sum()
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct CardConfig {
     pub number: u32,
@@ -56,6 +91,54 @@ pub struct CardConfig {
     pub card_type: String,
 }
 
+/// One rule violation found by `TradingCardSeries::lint`, located by a JSON path
+/// into the series file.
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct SeriesIssue {
+    pub rule: String,
+    pub path: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual: Option<Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cards: Vec<u32>,
+}
+
+impl SeriesIssue {
+    fn new(rule: &str, path: String) -> Self {
+        SeriesIssue {
+            rule: rule.to_string(),
+            path,
+            message: rule.to_string(),
+            expected: None,
+            actual: None,
+            cards: Vec::new(),
+        }
+    }
+
+    fn message(mut self, message: String) -> Self {
+        self.message = message;
+        self
+    }
+
+    fn expected(mut self, expected: Value) -> Self {
+        self.expected = Some(expected);
+        self
+    }
+
+    fn actual(mut self, actual: Value) -> Self {
+        self.actual = Some(actual);
+        self
+    }
+
+    fn cards(mut self, cards: Vec<u32>) -> Self {
+        self.cards = cards;
+        self
+    }
+}
+
 #[allow(dead_code)]
 impl TradingCardSeries {
     pub const MAXIMUM_RARITY_VALUE: u32 = u32::MAX;
@@ -67,6 +150,13 @@ impl TradingCardSeries {
     pub const ERROR_CARD_RARITIES_MISMATCH: &'static str = "Card rarities do not match the rarity distribution";
     pub const ERROR_RARITY_ORDER: &'static str = "Rarity order is not sensible";
     pub const ERROR_RARITY_RATIO: &'static str = "Rarity ratio exceeds 50% between tiers";
+    pub const ERROR_MINT_COUNT: &'static str = "You should mint at least one of each card";
+    pub const ERROR_MINT_SPECIALS_COUNT: &'static str = "Mint special cards shouldn't exceed more than 1/2.5 of printed cards";
+    pub const ERROR_MINT_SPECIALS_ORDER: &'static str = "Mint Special order is not sensible";
+    pub const ERROR_PACK_CONFIG: &'static str = "Pack products are not sensible";
+    pub const ERROR_DUPLICATE_CARD_NUMBER: &'static str = "Card numbers are not unique";
+    pub const ERROR_INVALID_JSON: &'static str = "Series file is not valid JSON";
+    pub const ERROR_SCHEMA: &'static str = "Series JSON does not match the series schema";
 
     pub fn from_file(series_file: &str) -> Result<Self> {
         let file = File::open(series_file)?;
@@ -95,6 +185,14 @@ impl TradingCardSeries {
         self.get_mint_each() * self.get_card_configs().len() as u32
     }
 
+    pub fn get_packs(&self) -> &HashMap<String, PackConfig> {
+        &self.config.packs
+    }
+
+    pub fn get_pack(&self, pack_id: &str) -> Option<&PackConfig> {
+        self.config.packs.get(pack_id)
+    }
+
     pub fn get_specials(&self) -> &HashMap<String, Special> {
         &self.config.distribution.mint.special
     }
@@ -125,51 +223,260 @@ impl TradingCardSeries {
         self.validate_series_values()
     }
 
+    /// Checks every series rule, reporting all violations together.
     pub fn validate_series_values(&self) -> Result<()> {
-        if self.id.is_empty() {
-            return Err(DeckForgeError::Validation { reason: Self::ERROR_NO_ID.to_string() });
+        let reasons = self.validation_errors();
+        if reasons.is_empty() {
+            Ok(())
+        } else {
+            Err(DeckForgeError::InvalidSeries { reasons })
         }
+    }
 
-        if self.name.is_empty() {
-            return Err(DeckForgeError::Validation { reason: Self::ERROR_NO_NAME.to_string() });
+    /// Every rule the series violates, in a fixed order.
+    pub fn validation_errors(&self) -> Vec<String> {
+        Self::violated_rules(&self.lint())
+    }
+
+    /// The distinct rules behind a list of issues, in first-seen order.
+    pub fn violated_rules(issues: &[SeriesIssue]) -> Vec<String> {
+        let mut reasons: Vec<String> = Vec::new();
+        for issue in issues {
+            if !reasons.contains(&issue.rule) {
+                reasons.push(issue.rule.clone());
+            }
         }
+        reasons
+    }
 
-        if self.description.is_empty() {
-            return Err(DeckForgeError::Validation { reason: Self::ERROR_NO_DESCRIPTION.to_string() });
+    /// Lints series JSON text, reporting parse and schema errors as issues too.
+    pub fn lint_json(series_data: &str) -> Vec<SeriesIssue> {
+        let series_json: Value = match serde_json::from_str(series_data) {
+            Ok(value) => value,
+            Err(e) => {
+                return vec![SeriesIssue::new(Self::ERROR_INVALID_JSON, "$".to_string())
+                    .message(e.to_string())]
+            }
+        };
+        match serde_json::from_value::<TradingCardSeries>(series_json) {
+            Ok(series) => series.lint(),
+            Err(e) => vec![SeriesIssue::new(Self::ERROR_SCHEMA, "$".to_string()).message(e.to_string())],
+        }
+    }
+
+    /// Lists every rule violation with its JSON path and details. A series is
+    /// valid exactly when this is empty.
+    pub fn lint(&self) -> Vec<SeriesIssue> {
+        let mut issues = Vec::new();
+        let rarity_path = "$.config.distribution.rarity";
+        let special_path = "$.config.distribution.mint.special";
+
+        let required = [
+            (self.id.is_empty(), Self::ERROR_NO_ID, "$.id"),
+            (self.name.is_empty(), Self::ERROR_NO_NAME, "$.name"),
+            (self.description.is_empty(), Self::ERROR_NO_DESCRIPTION, "$.description"),
+            (self.config.cards.is_empty(), Self::ERROR_NO_CARDS, "$.config.cards"),
+        ];
+        for (missing, rule, path) in required {
+            if missing {
+                issues.push(SeriesIssue::new(rule, path.to_string()));
+            }
         }
 
-        if self.config.cards.is_empty() {
-            return Err(DeckForgeError::Validation { reason: Self::ERROR_NO_CARDS.to_string() });
+        let mut seen = HashSet::new();
+        let mut duplicates = Vec::new();
+        for card in &self.config.cards {
+            if !seen.insert(card.number) && !duplicates.contains(&card.number) {
+                duplicates.push(card.number);
+            }
+        }
+        if !duplicates.is_empty() {
+            issues.push(
+                SeriesIssue::new(Self::ERROR_DUPLICATE_CARD_NUMBER, "$.config.cards".to_string())
+                    .cards(duplicates),
+            );
         }
 
+        let tiers = self.rarity_spec_as_sorted_vec();
         if !self.rarity_order_is_sensible() {
-            return Err(DeckForgeError::Validation { reason: Self::ERROR_RARITY_ORDER.to_string() });
+            let details = tiers
+                .windows(2)
+                .filter(|w| w[1].1 > w[0].1)
+                .map(|w| {
+                    SeriesIssue::new(Self::ERROR_RARITY_ORDER, format!("{}.{}.items", rarity_path, w[1].0))
+                        .message(format!("Rarity {} has more cards than rarity {}", w[1].0, w[0].0))
+                        .expected(json!({ "max": w[0].1 }))
+                        .actual(json!(w[1].1))
+                })
+                .collect();
+            Self::push_rule(&mut issues, Self::ERROR_RARITY_ORDER, rarity_path, details);
         }
 
         if !self.rarity_ratios_are_sensible() {
-            return Err(DeckForgeError::Validation { reason: Self::ERROR_RARITY_RATIO.to_string() });
+            let details = tiers
+                .windows(2)
+                .filter(|w| w[1].1 > w[0].1 / 2)
+                .map(|w| {
+                    SeriesIssue::new(Self::ERROR_RARITY_RATIO, format!("{}.{}.items", rarity_path, w[1].0))
+                        .message(format!("Rarity {} exceeds half of rarity {}", w[1].0, w[0].0))
+                        .expected(json!({ "max": w[0].1 / 2 }))
+                        .actual(json!(w[1].1))
+                })
+                .collect();
+            Self::push_rule(&mut issues, Self::ERROR_RARITY_RATIO, rarity_path, details);
         }
 
         if !self.total_cards_match() {
-            return Err(DeckForgeError::Validation { reason: Self::ERROR_TOTAL_CARDS_MISMATCH.to_string() });
+            let total_rarity_cards = self.total_rarity_cards();
+            issues.push(
+                SeriesIssue::new(Self::ERROR_TOTAL_CARDS_MISMATCH, "$.config.cards".to_string())
+                    .expected(json!(total_rarity_cards))
+                    .actual(json!(self.config.cards.len())),
+            );
         }
 
         if !self.card_rarities_match_card_configs() {
-            return Err(DeckForgeError::Validation { reason: Self::ERROR_CARD_RARITIES_MISMATCH.to_string() });
+            let specs = self.get_rarity_specs();
+            let mut rarities: Vec<u32> = specs.keys().copied().collect();
+            for card in &self.config.cards {
+                if !rarities.contains(&card.rarity) {
+                    rarities.push(card.rarity);
+                }
+            }
+            rarities.sort();
+
+            let mut details = Vec::new();
+            for rarity in rarities {
+                let cards: Vec<u32> = self
+                    .config
+                    .cards
+                    .iter()
+                    .filter(|c| c.rarity == rarity)
+                    .map(|c| c.number)
+                    .collect();
+                match specs.get(&rarity) {
+                    Some(items) if *items as usize != cards.len() => details.push(
+                        SeriesIssue::new(Self::ERROR_CARD_RARITIES_MISMATCH, format!("{}.{}.items", rarity_path, rarity))
+                            .message(format!("Rarity {} expects {} cards, {} are configured", rarity, items, cards.len()))
+                            .expected(json!(items))
+                            .actual(json!(cards.len())),
+                    ),
+                    None => details.push(
+                        SeriesIssue::new(Self::ERROR_CARD_RARITIES_MISMATCH, "$.config.cards".to_string())
+                            .message(format!("Cards use rarity {}, which the distribution does not define", rarity))
+                            .cards(cards),
+                    ),
+                    _ => {}
+                }
+            }
+            Self::push_rule(&mut issues, Self::ERROR_CARD_RARITIES_MISMATCH, rarity_path, details);
         }
 
         if !self.mint_count_is_reasonable() {
-            return Err(DeckForgeError::Validation { reason: "You should mint at least one of each card".to_string() });
+            issues.push(
+                SeriesIssue::new(Self::ERROR_MINT_COUNT, "$.config.distribution.mint.total".to_string())
+                    .expected(json!({ "min": 1 }))
+                    .actual(json!(self.get_mint_each())),
+            );
         }
 
         if !self.mint_specials_counts_are_sensible() {
-            return Err(DeckForgeError::Validation { reason: "Mint special cards shouldn't exceed more than 1/2.5 of printed cards".to_string() });
+            let mint_each = self.get_mint_each();
+            let total_specials = self.total_mint_specials();
+            let ratio = if mint_each > 0 { total_specials as f64 / mint_each as f64 } else { 0.0 };
+            issues.push(
+                SeriesIssue::new(Self::ERROR_MINT_SPECIALS_COUNT, special_path.to_string())
+                    .expected(json!({ "less_than": (mint_each as f32 / 2.5) as u32, "max_ratio": 0.4 }))
+                    .actual(json!({ "total": total_specials, "ratio": ratio })),
+            );
         }
 
         if !self.mint_special_order_is_sensible() {
-            return Err(DeckForgeError::Validation { reason: "Mint Special order is not sensible".to_string() });
+            let details = self
+                .get_mint_specials_as_sorted_vec()
+                .windows(2)
+                .filter(|w| w[1].1 > w[0].1 / 2)
+                .map(|w| {
+                    SeriesIssue::new(Self::ERROR_MINT_SPECIALS_ORDER, format!("{}.{}.items", special_path, w[1].0))
+                        .message(format!("Special {} exceeds half of special {}", w[1].0, w[0].0))
+                        .expected(json!({ "max": w[0].1 / 2 }))
+                        .actual(json!(w[1].1))
+                })
+                .collect();
+            Self::push_rule(&mut issues, Self::ERROR_MINT_SPECIALS_ORDER, special_path, details);
         }
-        Ok(())
+
+        if !self.packs_are_sensible() {
+            let rarities = self.get_rarity_specs();
+            let mut pack_ids: Vec<&String> = self.config.packs.keys().collect();
+            pack_ids.sort();
+
+            let mut details = Vec::new();
+            for pack_id in pack_ids {
+                let pack = &self.config.packs[pack_id];
+                let path = format!("$.config.packs.{}", pack_id);
+                let guaranteed = pack.guaranteed_cards();
+                if pack.cards == 0 {
+                    details.push(
+                        SeriesIssue::new(Self::ERROR_PACK_CONFIG, format!("{}.cards", path))
+                            .expected(json!({ "min": 1 }))
+                            .actual(json!(pack.cards)),
+                    );
+                }
+                if guaranteed > pack.cards as u64 {
+                    details.push(
+                        SeriesIssue::new(Self::ERROR_PACK_CONFIG, format!("{}.rarity_slots", path))
+                            .message("Guaranteed rarity slots exceed the cards per pack".to_string())
+                            .expected(json!({ "max": pack.cards }))
+                            .actual(json!(guaranteed)),
+                    );
+                }
+                let mut keys: Vec<&String> = pack.rarity_slots.keys().collect();
+                keys.sort();
+                for key in keys {
+                    if !key.parse::<u32>().is_ok_and(|k| rarities.contains_key(&k)) {
+                        details.push(
+                            SeriesIssue::new(Self::ERROR_PACK_CONFIG, format!("{}.rarity_slots.{}", path, key))
+                                .message(format!("Rarity slot '{}' is not a rarity of the series", key)),
+                        );
+                    }
+                }
+                if !(0.0..=1.0).contains(&pack.special_odds) {
+                    details.push(
+                        SeriesIssue::new(Self::ERROR_PACK_CONFIG, format!("{}.special_odds", path))
+                            .expected(json!({ "min": 0.0, "max": 1.0 }))
+                            .actual(json!(pack.special_odds)),
+                    );
+                }
+            }
+            Self::push_rule(&mut issues, Self::ERROR_PACK_CONFIG, "$.config.packs", details);
+        }
+
+        issues
+    }
+
+    /// Adds the detailed issues of a failed rule, or a single issue at `path`
+    /// when no specific location could be pinned down.
+    fn push_rule(issues: &mut Vec<SeriesIssue>, rule: &str, path: &str, details: Vec<SeriesIssue>) {
+        if details.is_empty() {
+            issues.push(SeriesIssue::new(rule, path.to_string()));
+        } else {
+            issues.extend(details);
+        }
+    }
+
+    /// Every pack must hold at least one card, guarantee no more cards than it
+    /// holds, only guarantee rarities the series defines, and have odds in [0, 1].
+    pub fn packs_are_sensible(&self) -> bool {
+        let rarities = self.get_rarity_specs();
+        self.config.packs.values().all(|pack| {
+            pack.cards > 0
+                && pack.guaranteed_cards() <= pack.cards as u64
+                && pack.rarity_slots.keys().all(|key| {
+                    key.parse::<u32>().is_ok_and(|k| rarities.contains_key(&k))
+                })
+                && (0.0..=1.0).contains(&pack.special_odds)
+        })
     }
 
     pub fn mint_special_order_is_sensible(&self) -> bool {
@@ -185,10 +492,14 @@ impl TradingCardSeries {
     }
 
     pub fn mint_specials_counts_are_sensible(&self) -> bool {
-        let mint_specials = self.get_mint_specials_specs();
         let mint_each = self.get_mint_each();
-        let total_specials: u32 = mint_specials.values().sum();
-        total_specials < (mint_each as f32 / 2.5) as u32
+        self.total_mint_specials() < (mint_each as f32 / 2.5) as u64
+    }
+
+    /// Total special items across all specials, summed wide so that no file
+    /// can overflow it.
+    pub fn total_mint_specials(&self) -> u64 {
+        self.config.distribution.mint.special.values().map(|special| special.items as u64).sum()
     }
 
     pub fn mint_count_is_reasonable(&self) -> bool {
@@ -246,18 +557,29 @@ impl TradingCardSeries {
             .collect()
     }
 
+    /// Display name of a rarity tier, e.g. "Common" for rarity 1.
+    pub fn rarity_name(&self, rarity: u32) -> Option<&str> {
+        self.config
+            .distribution
+            .rarity
+            .get(&rarity.to_string())
+            .map(|r| r.name.as_str())
+    }
+
     pub fn rarity_spec_as_sorted_vec(&self) -> Vec<(u32, u32)> {
         let mut vec: Vec<(u32, u32)> = self.get_rarity_specs().into_iter().collect();
-        vec.sort_by(|a, b| a.0.cmp(&b.0));
+        vec.sort_by_key(|a| a.0);
         vec
     }
 
     pub fn total_cards_match(&self) -> bool {
-        let total_card_configs = self.config.cards.len() as u32;
-
-        let total_rarity_cards: u32 = self.config.distribution.rarity.values().map(|r| r.items).sum();
+        self.config.cards.len() as u64 == self.total_rarity_cards()
+    }
 
-        total_card_configs == total_rarity_cards
+    /// Total cards across all rarity tiers, summed wide so that no file can
+    /// overflow it.
+    pub fn total_rarity_cards(&self) -> u64 {
+        self.config.distribution.rarity.values().map(|r| r.items as u64).sum()
     }
 
     pub fn card_rarities_match_card_configs(&self) -> bool {
@@ -277,7 +599,6 @@ pub mod tests {
     use super::*;
 
     use rand::seq::SliceRandom;
-    use serde_json::Value;
 
     #[test]
     fn test_series_ok() {
@@ -296,6 +617,90 @@ pub mod tests {
         assert!(err_msg.contains(TradingCardSeries::ERROR_NO_ID), "got: {}", err_msg);
     }
 
+    #[test]
+    fn test_series_reports_every_violation() {
+        let mut series = test_series_data();
+        series.name = "".to_string();
+        series.config.distribution.mint.total = 0;
+        let err = series.validate_series_values().unwrap_err();
+        match err {
+            DeckForgeError::InvalidSeries { reasons } => {
+                assert!(reasons.contains(&TradingCardSeries::ERROR_NO_NAME.to_string()));
+                assert!(reasons.contains(&TradingCardSeries::ERROR_MINT_COUNT.to_string()));
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn test_lint_valid_series() {
+        let series = test_series_data();
+        assert!(series.lint().is_empty());
+    }
+
+    #[test]
+    fn test_lint_reports_paths_and_cards() {
+        let mut series = test_series_data();
+        series.config.cards[0].rarity = 7;
+        series.config.cards[2].number = series.config.cards[1].number;
+        let issues = series.lint();
+
+        let undefined = issues
+            .iter()
+            .find(|i| i.rule == TradingCardSeries::ERROR_CARD_RARITIES_MISMATCH && i.path == "$.config.cards")
+            .unwrap();
+        assert_eq!(undefined.cards, vec![1]);
+
+        let common = issues
+            .iter()
+            .find(|i| i.path == "$.config.distribution.rarity.1.items")
+            .unwrap();
+        assert_eq!(common.expected, Some(json!(124)));
+        assert_eq!(common.actual, Some(json!(123)));
+
+        let duplicate = issues
+            .iter()
+            .find(|i| i.rule == TradingCardSeries::ERROR_DUPLICATE_CARD_NUMBER)
+            .unwrap();
+        assert_eq!(duplicate.cards, vec![2]);
+    }
+
+    #[test]
+    fn test_lint_huge_counts_do_not_overflow() {
+        let mut series = test_series_data();
+        for rarity in series.config.distribution.rarity.values_mut() {
+            rarity.items = u32::MAX;
+        }
+        for special in series.config.distribution.mint.special.values_mut() {
+            special.items = u32::MAX;
+        }
+        for pack in series.config.packs.values_mut() {
+            pack.rarity_slots = HashMap::from([("1".to_string(), u32::MAX), ("2".to_string(), u32::MAX)]);
+        }
+        let issues = series.lint();
+
+        let rarity_count = series.config.distribution.rarity.len() as u64;
+        let total = issues
+            .iter()
+            .find(|i| i.rule == TradingCardSeries::ERROR_TOTAL_CARDS_MISMATCH)
+            .unwrap();
+        assert_eq!(total.expected, Some(json!(u32::MAX as u64 * rarity_count)));
+        assert!(issues.iter().any(|i| i.rule == TradingCardSeries::ERROR_MINT_SPECIALS_COUNT));
+        assert!(issues
+            .iter()
+            .any(|i| i.rule == TradingCardSeries::ERROR_PACK_CONFIG && i.actual == Some(json!(u32::MAX as u64 * 2))));
+    }
+
+    #[test]
+    fn test_lint_json_parse_error() {
+        let issues = TradingCardSeries::lint_json("{ not json");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].rule, TradingCardSeries::ERROR_INVALID_JSON);
+
+        let issues = TradingCardSeries::lint_json("{}");
+        assert_eq!(issues[0].rule, TradingCardSeries::ERROR_SCHEMA);
+    }
+
     #[test]
     fn test_series_data_from_file() {
         let series = test_series_data();
@@ -416,7 +821,7 @@ pub mod tests {
 
     #[test]
     fn test_mint_specials_order_is_sensible() {
-        let mut series = test_series_data();
+        let series = test_series_data();
         let mint_specials = series.get_mint_specials_as_sorted_vec();
         let mut last_special = (TradingCardSeries::MAXIMUM_RARITY_VALUE / 2) - 1;
         for (_key, special) in mint_specials.iter() {
@@ -471,6 +876,22 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn test_packs_are_sensible() {
+        let mut series = test_series_data();
+        assert!(series.packs_are_sensible());
+        assert_eq!(series.get_pack("booster").unwrap().rarity_slots_as_sorted_vec(), vec![(3, 1), (2, 3)]);
+
+        let pack = series.config.packs.get_mut("booster").unwrap();
+        pack.rarity_slots.insert("9".to_string(), 1);
+        assert!(!series.packs_are_sensible());
+
+        let pack = series.config.packs.get_mut("booster").unwrap();
+        pack.rarity_slots.remove("9");
+        pack.cards = 2;
+        assert!(!series.packs_are_sensible());
+    }
+
     #[test]
     fn test_get_mint_each() {
         let series = test_series_data();
@@ -1,12 +1,43 @@
+use std::collections::BTreeMap;
+
 use rand::seq::SliceRandom;
 use rand::{rngs::StdRng, Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
 
 use crate::blockchain::deckchain::DeckChain;
-use crate::error::Result;
+use crate::blockchain::ledger::CardLedger;
+use crate::error::{DeckForgeError, Result};
 
 use super::card::TradingCard;
-use super::series::TradingCardSeries;
+use super::series::{PackConfig, TradingCardSeries};
+
+/// Minted and still undealt cards of one rarity.
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct RarityAvailability {
+    pub rarity: u32,
+    pub name: Option<String>,
+    pub minted: u64,
+    pub remaining: u64,
+}
+
+/// Minted and still undealt cards with one special finish.
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct SpecialAvailability {
+    pub name: String,
+    pub minted: u64,
+    pub remaining: u64,
+}
+
+/// How much of a released series is left to deal. A card is undealt while
+/// the null address holds it, exactly as `deal_pack` decides.
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct SeriesAvailability {
+    pub total_minted: u64,
+    pub remaining: u64,
+    pub rarities: Vec<RarityAvailability>,
+    pub specials: Vec<SpecialAvailability>,
+}
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct TradingCardSeriesReleaseState {
@@ -18,15 +49,26 @@ pub struct TradingCardSeriesReleaseState {
 
 #[allow(dead_code)]
 impl TradingCardSeriesReleaseState {
+    /// Rebuilds a release from the chain. The shuffled deck is only built when
+    /// the salt is known, either revealed on chain or held in the data directory.
     pub fn from_deckchain(deckchain: &DeckChain, series_id: String) -> Result<Self> {
         let series = TradingCardSeries::from_deckchain(deckchain, series_id.clone())?;
 
-        Ok(TradingCardSeriesReleaseState {
+        let mut release = TradingCardSeriesReleaseState {
             id: series_id,
             series,
             released_cards: Vec::new(),
             shuffle_hash: String::new(),
-        })
+        };
+
+        if let Some((shuffle_hash, _commitment)) = deckchain.shuffle_commitment(&release.id) {
+            release.shuffle_hash = shuffle_hash;
+            if let Some(private_salt) = deckchain.shuffle_salt(&release.id)? {
+                release.build_cards(private_salt);
+            }
+        }
+
+        Ok(release)
     }
 
     pub fn new_from_series(series: TradingCardSeries, private_salt: [u8; 16]) -> Self {
@@ -42,9 +84,40 @@ impl TradingCardSeriesReleaseState {
         release
     }
 
+    /// Builds a release from a known shuffle hash and salt, e.g. to try out
+    /// many shuffles of a series before committing it to the chain.
+    pub fn new_with_shuffle(
+        series: TradingCardSeries,
+        shuffle_hash: [u8; 16],
+        private_salt: [u8; 16],
+    ) -> Self {
+        let mut release = TradingCardSeriesReleaseState {
+            id: series.id.clone(),
+            series,
+            released_cards: Vec::new(),
+            shuffle_hash: hex::encode(shuffle_hash),
+        };
+        release.build_cards(private_salt);
+        release
+    }
+
     pub fn build_cards(&mut self, private_salt: [u8; 16]) {
-        let card_configs = self.series.get_card_configs();
-        let mint_list = self.series.get_mint_list();
+        let mut card_deck = TradingCardSeriesReleaseState::mint_cards(&self.series);
+
+        let mut seeded_rng = TradingCardSeriesReleaseState::get_seeded_rng(
+            self.get_shuffle_hash(),
+            private_salt,
+        );
+
+        card_deck.shuffle(&mut seeded_rng);
+        self.released_cards = card_deck;
+    }
+
+    /// Generates every card of a series in mint order (unshuffled): each card
+    /// config in turn, with one card per entry of the mint list.
+    pub fn mint_cards(series: &TradingCardSeries) -> Vec<TradingCard> {
+        let card_configs = series.get_card_configs();
+        let mint_list = series.get_mint_list();
         let mint_list_length = mint_list.len() as u32;
 
         let mut card_deck: Vec<TradingCard> = Vec::with_capacity(
@@ -56,7 +129,7 @@ impl TradingCardSeriesReleaseState {
                 let card = TradingCard::from_card_config(
                     card_config,
                     properties.clone(),
-                    self.id.clone(),
+                    series.id.clone(),
                     TradingCardSeriesReleaseState::format_serial(
                         (idx + 1) as u32,
                         mint_list_length,
@@ -66,16 +139,138 @@ impl TradingCardSeriesReleaseState {
             }
         }
 
-        let mut seeded_rng = TradingCardSeriesReleaseState::get_seeded_rng(
-            self.get_shuffle_hash(),
-            private_salt,
-        );
+        card_deck
+    }
 
-        card_deck.shuffle(&mut seeded_rng);
-        self.released_cards = card_deck;
+    /// Deals one pack from the shuffled deck.
+    ///
+    /// Guaranteed rarity slots are filled first (highest rarity first), then a
+    /// special-finish card is added with the pack's odds, and the remaining
+    /// slots take the next cards in deck order. Cards for which `is_available`
+    /// is false have already been dealt and are skipped. The special-finish
+    /// roll is seeded from the shuffle seed and `pack_index`, so a deal can be
+    /// replayed once the salt is revealed.
+    pub fn deal_pack<F>(
+        &self,
+        pack_id: &str,
+        pack: &PackConfig,
+        private_salt: [u8; 16],
+        pack_index: u64,
+        is_available: F,
+    ) -> Result<Vec<TradingCard>>
+    where
+        F: Fn(&TradingCard) -> bool,
+    {
+        let sold_out = || DeckForgeError::PackSoldOut {
+            series_id: self.id.clone(),
+            pack_id: pack_id.to_string(),
+        };
+
+        let mut rng = self.get_pack_rng(private_salt, pack_index);
+        let mut taken: Vec<usize> = Vec::with_capacity(pack.cards as usize);
+        let next_card = |taken: &Vec<usize>, wanted: &dyn Fn(&TradingCard) -> bool| {
+            self.released_cards
+                .iter()
+                .enumerate()
+                .position(|(idx, card)| !taken.contains(&idx) && is_available(card) && wanted(card))
+        };
+
+        for (rarity, count) in pack.rarity_slots_as_sorted_vec() {
+            for _ in 0..count {
+                let idx = next_card(&taken, &|card| card.rarity() == rarity).ok_or_else(sold_out)?;
+                taken.push(idx);
+            }
+        }
+
+        if (taken.len() as u32) < pack.cards
+            && pack.special_odds > 0.0
+            && rng.gen_bool(pack.special_odds)
+        {
+            if let Some(idx) = next_card(&taken, &|card| card.is_special()) {
+                taken.push(idx);
+            }
+        }
+
+        while (taken.len() as u32) < pack.cards {
+            let idx = next_card(&taken, &|_| true).ok_or_else(sold_out)?;
+            taken.push(idx);
+        }
+
+        Ok(taken.into_iter().map(|idx| self.released_cards[idx].clone()).collect())
+    }
+
+    fn get_pack_rng(&self, private_salt: [u8; 16], pack_index: u64) -> StdRng {
+        let mut hasher = Sha3_256::new();
+        hasher.update(self.get_shuffle_hash());
+        hasher.update(private_salt);
+        hasher.update(pack_index.to_le_bytes());
+        StdRng::from_seed(hasher.finalize().into())
     }
 
-    fn format_serial(serial: u32, mint_count: u32) -> String {
+    /// The public commitment to a private salt: its hex Sha3-256 digest.
+    pub fn salt_commitment(private_salt: &[u8; 16]) -> String {
+        hex::encode(Sha3_256::digest(private_salt))
+    }
+
+    pub fn generate_private_salt() -> [u8; 16] {
+        let mut rng = rand::thread_rng();
+        let mut salt = [0u8; 16];
+        rng.fill(&mut salt);
+        salt
+    }
+
+    pub fn parse_salt(salt: &str) -> Result<[u8; 16]> {
+        hex::decode(salt)?.try_into().map_err(|_| DeckForgeError::Validation {
+            reason: "Shuffle salt must be 16 bytes of hex".to_string(),
+        })
+    }
+
+    /// Counts the series' minted and undealt cards, overall and per rarity
+    /// and special finish.
+    pub fn availability(&self, ledger: &CardLedger) -> SeriesAvailability {
+        let mut rarities: BTreeMap<u32, (u64, u64)> = BTreeMap::new();
+        let mut specials: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+        let (mut total_minted, mut remaining) = (0, 0);
+
+        for card in TradingCardSeriesReleaseState::mint_cards(&self.series) {
+            let card_id = card.card_id();
+            if ledger.owner_of(&card_id).is_none() {
+                continue;
+            }
+            let undealt = u64::from(ledger.is_undealt(&self.id, &card_id));
+            total_minted += 1;
+            remaining += undealt;
+
+            let counts = rarities.entry(card.rarity()).or_default();
+            counts.0 += 1;
+            counts.1 += undealt;
+            for special in card.properties.iter().filter(|p| !p.is_empty()) {
+                let counts = specials.entry(special.clone()).or_default();
+                counts.0 += 1;
+                counts.1 += undealt;
+            }
+        }
+
+        SeriesAvailability {
+            total_minted,
+            remaining,
+            rarities: rarities
+                .into_iter()
+                .map(|(rarity, (minted, remaining))| RarityAvailability {
+                    rarity,
+                    name: self.series.rarity_name(rarity).map(str::to_string),
+                    minted,
+                    remaining,
+                })
+                .collect(),
+            specials: specials
+                .into_iter()
+                .map(|(name, (minted, remaining))| SpecialAvailability { name, minted, remaining })
+                .collect(),
+        }
+    }
+
+    pub(crate) fn format_serial(serial: u32, mint_count: u32) -> String {
         let mint_count_length = mint_count.to_string().len();
         format!("{:0width$}", serial, width = mint_count_length)
     }
@@ -162,6 +357,14 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_mint_cards_have_unique_ids() {
+        let series = test_series_data();
+        let cards = TradingCardSeriesReleaseState::mint_cards(&series);
+        let ids: std::collections::HashSet<String> = cards.iter().map(|c| c.card_id()).collect();
+        assert_eq!(ids.len() as u32, series.get_mint_total());
+    }
+
     #[test]
     fn test_repeatable_shuffle_seed() {
         let series_release = get_testing_release();
@@ -190,6 +393,55 @@ mod tests {
         release
     }
 
+    #[test]
+    fn test_deal_pack() {
+        let series_release = get_testing_release();
+        let (_shuffle_hash, private_salt) = get_testing_hash_salt();
+        let pack = series_release.series.get_pack("booster").unwrap().clone();
+
+        let cards = series_release
+            .deal_pack("booster", &pack, private_salt, 0, |_| true)
+            .unwrap();
+        assert_eq!(cards.len() as u32, pack.cards);
+        assert_eq!(cards.iter().filter(|c| c.rarity() == 3).count(), 1);
+        assert!(cards.iter().filter(|c| c.rarity() == 2).count() >= 3);
+
+        let again = series_release
+            .deal_pack("booster", &pack, private_salt, 0, |_| true)
+            .unwrap();
+        assert_eq!(cards, again);
+
+        let dealt: std::collections::HashSet<String> = cards.iter().map(|c| c.card_id()).collect();
+        let next = series_release
+            .deal_pack("booster", &pack, private_salt, 1, |c| !dealt.contains(&c.card_id()))
+            .unwrap();
+        assert!(next.iter().all(|c| !dealt.contains(&c.card_id())));
+    }
+
+    #[test]
+    fn test_deal_pack_sold_out() {
+        let series_release = get_testing_release();
+        let (_shuffle_hash, private_salt) = get_testing_hash_salt();
+        let pack = series_release.series.get_pack("booster").unwrap().clone();
+
+        let result = series_release.deal_pack("booster", &pack, private_salt, 0, |c| c.rarity() != 3);
+        assert!(matches!(result, Err(DeckForgeError::PackSoldOut { .. })));
+    }
+
+    #[test]
+    fn test_salt_commitment() {
+        let (_shuffle_hash, private_salt) = get_testing_hash_salt();
+        let commitment = TradingCardSeriesReleaseState::salt_commitment(&private_salt);
+        assert_eq!(commitment.len(), 64);
+        assert_ne!(
+            commitment,
+            TradingCardSeriesReleaseState::salt_commitment(&[0u8; 16])
+        );
+        let parsed = TradingCardSeriesReleaseState::parse_salt(&hex::encode(private_salt)).unwrap();
+        assert_eq!(parsed, private_salt);
+        assert!(TradingCardSeriesReleaseState::parse_salt("abcd").is_err());
+    }
+
     fn get_testing_hash_salt() -> ([u8; 16], [u8; 16]) {
         let private_salt = "76f38e455a57ed4003bfd1a1c83cc9e5";
         let shuffle_hash = "935a5191ff1e7dbd10df7f0957da72ae";
@@ -0,0 +1,233 @@
+use std::collections::{BTreeMap, HashSet};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Serialize;
+
+use super::card::TradingCard;
+use super::series::TradingCardSeries;
+use super::seriesreleasestate::TradingCardSeriesReleaseState;
+
+/// Number of packs of each configured product dealt per simulated shuffle.
+pub const PACKS_PER_RUN: u64 = 50;
+
+#[derive(Clone, Serialize, Debug)]
+pub struct RarityOdds {
+    pub rarity: u32,
+    pub name: String,
+    pub cards: u64,
+    pub probability: f64,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct SpecialOdds {
+    pub name: String,
+    pub cards: u64,
+    pub probability: f64,
+    pub serial_first: String,
+    pub serial_last: String,
+}
+
+/// Average contents of a pack of `cards` cards.
+#[derive(Clone, Serialize, Debug)]
+pub struct PackOdds {
+    pub pack_id: Option<String>,
+    pub cards: u32,
+    pub packs: u64,
+    pub expected_rarities: BTreeMap<u32, f64>,
+    pub expected_specials: BTreeMap<String, f64>,
+    pub chance_of_special: f64,
+}
+
+/// Statistics gathered over many shuffles of a series that has not been released.
+#[derive(Clone, Serialize, Debug)]
+pub struct SimulationReport {
+    pub series_id: String,
+    pub runs: u32,
+    pub seed: u64,
+    pub deck_size: usize,
+    pub rarities: Vec<RarityOdds>,
+    pub specials: Vec<SpecialOdds>,
+    pub pack_sizes: Vec<PackOdds>,
+    pub packs: Vec<PackOdds>,
+}
+
+#[derive(Default)]
+struct PackTally {
+    packs: u64,
+    with_special: u64,
+    rarities: BTreeMap<u32, u64>,
+    specials: BTreeMap<String, u64>,
+}
+
+impl PackTally {
+    fn add(&mut self, cards: &[TradingCard]) {
+        self.packs += 1;
+        if cards.iter().any(|c| c.is_special()) {
+            self.with_special += 1;
+        }
+        for card in cards {
+            *self.rarities.entry(card.rarity()).or_insert(0) += 1;
+            if let Some(finish) = special_finish(card) {
+                *self.specials.entry(finish.to_string()).or_insert(0) += 1;
+            }
+        }
+    }
+
+    fn odds(&self, pack_id: Option<String>, cards: u32) -> PackOdds {
+        let packs = self.packs.max(1) as f64;
+        PackOdds {
+            pack_id,
+            cards,
+            packs: self.packs,
+            expected_rarities: self.rarities.iter().map(|(r, n)| (*r, *n as f64 / packs)).collect(),
+            expected_specials: self.specials.iter().map(|(s, n)| (s.clone(), *n as f64 / packs)).collect(),
+            chance_of_special: self.with_special as f64 / packs,
+        }
+    }
+}
+
+fn special_finish(card: &TradingCard) -> Option<&str> {
+    card.properties.iter().find(|p| !p.is_empty()).map(|p| p.as_str())
+}
+
+/// Shuffles the series `runs` times with shuffle hashes and salts drawn from
+/// `seed`, dealing each deck into packs of every size in `pack_sizes` and into
+/// `PACKS_PER_RUN` packs of every configured pack product.
+pub fn simulate_series(
+    series: &TradingCardSeries,
+    runs: u32,
+    seed: u64,
+    pack_sizes: &[u32],
+) -> SimulationReport {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut rarity_counts: BTreeMap<u32, u64> = BTreeMap::new();
+    let mut special_counts: BTreeMap<String, (u64, String, String)> = BTreeMap::new();
+    let mut size_tallies: Vec<PackTally> = pack_sizes.iter().map(|_| PackTally::default()).collect();
+
+    let mut pack_ids: Vec<&String> = series.get_packs().keys().collect();
+    pack_ids.sort();
+    let mut pack_tallies: Vec<PackTally> = pack_ids.iter().map(|_| PackTally::default()).collect();
+
+    let mut deck_size = 0;
+    for _ in 0..runs {
+        let salt = rng.gen();
+        let release = TradingCardSeriesReleaseState::new_with_shuffle(series.clone(), rng.gen(), salt);
+        let deck = &release.released_cards;
+        deck_size = deck.len();
+
+        for card in deck {
+            *rarity_counts.entry(card.rarity()).or_insert(0) += 1;
+            if let Some(finish) = special_finish(card) {
+                let entry = special_counts
+                    .entry(finish.to_string())
+                    .or_insert_with(|| (0, card.serial().to_string(), card.serial().to_string()));
+                entry.0 += 1;
+                if card.serial() < entry.1.as_str() {
+                    entry.1 = card.serial().to_string();
+                }
+                if card.serial() > entry.2.as_str() {
+                    entry.2 = card.serial().to_string();
+                }
+            }
+        }
+
+        for (size, tally) in pack_sizes.iter().zip(size_tallies.iter_mut()) {
+            if *size > 0 {
+                for pack in deck.chunks_exact(*size as usize) {
+                    tally.add(pack);
+                }
+            }
+        }
+
+        for (pack_id, tally) in pack_ids.iter().zip(pack_tallies.iter_mut()) {
+            let pack = &series.get_packs()[*pack_id];
+            let mut dealt: HashSet<String> = HashSet::new();
+            for pack_index in 0..PACKS_PER_RUN {
+                let is_available = |card: &TradingCard| !dealt.contains(&card.card_id());
+                match release.deal_pack(pack_id, pack, salt, pack_index, is_available) {
+                    Ok(cards) => {
+                        dealt.extend(cards.iter().map(|c| c.card_id()));
+                        tally.add(&cards);
+                    }
+                    Err(_) => break,
+                }
+            }
+        }
+    }
+
+    let total_cards = (deck_size as u64 * runs as u64).max(1) as f64;
+    SimulationReport {
+        series_id: series.id.clone(),
+        runs,
+        seed,
+        deck_size,
+        rarities: rarity_counts
+            .into_iter()
+            .map(|(rarity, cards)| RarityOdds {
+                rarity,
+                name: series.rarity_name(rarity).unwrap_or_default().to_string(),
+                cards: cards / runs.max(1) as u64,
+                probability: cards as f64 / total_cards,
+            })
+            .collect(),
+        specials: special_counts
+            .into_iter()
+            .map(|(name, (cards, serial_first, serial_last))| SpecialOdds {
+                name,
+                cards: cards / runs.max(1) as u64,
+                probability: cards as f64 / total_cards,
+                serial_first,
+                serial_last,
+            })
+            .collect(),
+        pack_sizes: pack_sizes
+            .iter()
+            .zip(size_tallies.iter())
+            .map(|(size, tally)| tally.odds(None, *size))
+            .collect(),
+        packs: pack_ids
+            .iter()
+            .zip(pack_tallies.iter())
+            .map(|(pack_id, tally)| tally.odds(Some(pack_id.to_string()), series.get_packs()[*pack_id].cards))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::card::series::tests::test_series_data;
+
+    #[test]
+    fn test_simulation_matches_distribution() {
+        let series = test_series_data();
+        let report = simulate_series(&series, 2, 7, &[10]);
+
+        assert_eq!(report.deck_size as u32, series.get_mint_total());
+        let common = report.rarities.iter().find(|r| r.rarity == 1).unwrap();
+        assert_eq!(common.name, "Common");
+        assert_eq!(common.cards, 124 * 242);
+        assert!((common.probability - 124.0 / 201.0).abs() < 1e-9);
+
+        let holo = report.specials.iter().find(|s| s.name == "holographic").unwrap();
+        assert_eq!(holo.cards, 7 * 201);
+        assert!(holo.serial_first <= holo.serial_last);
+
+        let expected: f64 = report.pack_sizes[0].expected_rarities.values().sum();
+        assert!((expected - 10.0).abs() < 1e-9);
+
+        let booster = &report.packs[0];
+        assert_eq!(booster.packs, 2 * PACKS_PER_RUN);
+        assert!(booster.expected_rarities[&2] >= 3.0);
+    }
+
+    #[test]
+    fn test_simulation_is_reproducible() {
+        let series = test_series_data();
+        let a = simulate_series(&series, 1, 42, &[5]);
+        let b = simulate_series(&series, 1, 42, &[5]);
+        assert_eq!(a.pack_sizes[0].chance_of_special, b.pack_sizes[0].chance_of_special);
+        assert_eq!(a.packs[0].expected_specials, b.packs[0].expected_specials);
+    }
+}
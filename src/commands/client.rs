@@ -0,0 +1,56 @@
+use std::fs::read_to_string;
+
+use reqwest::header::CONTENT_TYPE;
+use serde_json::Value;
+
+use crate::auth::signing::SignedHeaders;
+use crate::commands::keys::key_password;
+use crate::crypto::keypair::KeyPair;
+use crate::error::{DeckForgeError, Result};
+
+/// Sends a POST signed with the key in `key_file` (or `$DECKFORGE_KEY_FILE`)
+/// to a running API server, returning the JSON response of a successful
+/// request. Writing through the server keeps it the only writer of its
+/// blockchain file.
+pub async fn post_signed(server: &str, path: &str, key_file: Option<String>, body: Vec<u8>) -> Result<Value> {
+    let key_file = key_file
+        .or_else(|| std::env::var("DECKFORGE_KEY_FILE").ok())
+        .ok_or(DeckForgeError::MissingSigningKey)?;
+    let pem = read_to_string(key_file)?;
+    let password = if KeyPair::is_encrypted_pem(&pem) {
+        Some(key_password(false)?)
+    } else {
+        None
+    };
+    let key_pair = KeyPair::from_pem(&pem, password.as_deref())?;
+
+    let url = reqwest::Url::parse(&format!("{}{}", server.trim_end_matches('/'), path))
+        .map_err(|e| DeckForgeError::Validation {
+            reason: format!("Invalid server URL '{}': {}", server, e),
+        })?;
+    let signed = SignedHeaders::sign(&key_pair, "POST", url.path(), &body);
+
+    let mut request = reqwest::Client::new()
+        .post(url)
+        .header(CONTENT_TYPE, "application/json")
+        .body(body);
+    for (name, value) in signed.to_pairs() {
+        request = request.header(name, value);
+    }
+    let resp = request.send().await?;
+
+    let status = resp.status();
+    let body: Value = resp.json().await.unwrap_or(Value::Null);
+    if !status.is_success() {
+        let message = body
+            .get("error")
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown error")
+            .to_string();
+        return Err(DeckForgeError::ServerRejected {
+            status: status.as_u16(),
+            message,
+        });
+    }
+    Ok(body)
+}
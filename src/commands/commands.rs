@@ -1,5 +1,8 @@
 use clap::Subcommand;
 
+use crate::auth::keys::Scope;
+use crate::blockchain::storage::StorageKind;
+
 #[derive(Subcommand)]
 pub enum Commands {
     GenerateKey {
@@ -8,10 +11,198 @@ pub enum Commands {
 
         #[arg(short, long)]
         expiry: Option<String>,
+
+        /// Grant the key every scope
+        #[arg(long, conflicts_with = "scopes")]
+        admin: bool,
+
+        /// Scopes to grant: read, transfer, release, key-admin
+        /// (default: read,transfer)
+        #[arg(long = "scope", value_delimiter = ',')]
+        scopes: Vec<Scope>,
+
+        /// Write the key pair to this password-encrypted key file, for signing
+        /// API requests (password from $DECKFORGE_KEY_PASSWORD or a prompt)
+        #[arg(long)]
+        pem: Option<String>,
+
+        /// Print the secret key in plaintext to stdout
+        #[arg(long)]
+        insecure_print_secret: bool,
+
+        /// Add the key to the authorized_keys file without asking
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// List, revoke, extend, rotate or import authorized keys
+    Keys {
+        #[command(subcommand)]
+        command: KeysCommand,
     },
     StartServer,
     InsertReleaseSet {
         #[arg(short, long)]
         series_file: String,
+
+        /// Release through a running API server (e.g. http://127.0.0.1:3000)
+        /// instead of writing the chain file directly
+        #[arg(long)]
+        server: Option<String>,
+
+        /// Key file of the key (with the release scope) signing the request for --server
+        /// (defaults to $DECKFORGE_KEY_FILE; encrypted files are unlocked with
+        /// $DECKFORGE_KEY_PASSWORD or a prompt)
+        #[arg(long)]
+        key_file: Option<String>,
+    },
+    /// Report every problem in a series file without releasing it
+    LintSeries {
+        #[arg(short, long)]
+        series_file: String,
+
+        /// Print the issues as machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Truncate a quarantined or damaged chain back to its last valid block
+    RepairChain,
+    /// Show a confirmed transaction with its block and confirmation count
+    ShowTx {
+        /// Transaction id, as returned in API receipts
+        id: String,
+
+        /// Print the transaction as machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Copy the chain to another storage backend (json, log or sqlite)
+    MigrateStorage {
+        /// Backend to copy from (defaults to the configured one)
+        #[arg(long)]
+        from: Option<StorageKind>,
+
+        #[arg(long)]
+        to: StorageKind,
+    },
+    /// Shuffle a series file many times and report its real pull odds
+    Simulate {
+        #[arg(short, long)]
+        series_file: String,
+
+        #[arg(short, long, default_value_t = 100)]
+        runs: u32,
+
+        /// Seed for the simulated shuffles (random when omitted)
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// Pack sizes to report expected pulls for
+        #[arg(short, long, value_delimiter = ',', default_value = "5,10,15")]
+        pack_sizes: Vec<u32>,
+
+        /// Print the report as machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+    OpenPack {
+        #[arg(short, long)]
+        series_id: String,
+
+        #[arg(short, long)]
+        pack: String,
+
+        /// Wallet address receiving the cards
+        #[arg(short, long)]
+        receiver: String,
+
+        /// Open the pack through a running API server instead of writing the
+        /// chain file directly
+        #[arg(long)]
+        server: Option<String>,
+
+        /// Key file of the key (with the transfer scope) signing the request for --server
+        /// (defaults to $DECKFORGE_KEY_FILE)
+        #[arg(long)]
+        key_file: Option<String>,
+    },
+    RevealShuffle {
+        #[arg(short, long)]
+        series_id: String,
+
+        /// Reveal through the running API server that released the series
+        /// (it holds the salt) instead of writing the chain file directly
+        #[arg(long)]
+        server: Option<String>,
+
+        /// Key file of the key (with the release scope) signing the request for --server
+        /// (defaults to $DECKFORGE_KEY_FILE)
+        #[arg(long)]
+        key_file: Option<String>,
+    },
+    VerifyShuffle {
+        #[arg(short, long)]
+        series_id: String,
+
+        /// Print every card id in shuffled order
+        #[arg(short, long)]
+        list: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum KeysCommand {
+    /// List the authorized keys with their fingerprint, expiry and scopes
+    List {
+        /// Print the keys as machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Remove a key from the authorized_keys file
+    Revoke {
+        /// Label or public key of the key
+        key: String,
+    },
+    /// Set a new expiry for a key
+    Extend {
+        /// Label or public key of the key
+        key: String,
+
+        /// New expiry date (ISO 8601 format)
+        #[arg(short, long)]
+        expiry: String,
+    },
+    /// Replace a key with a new key pair, keeping its label, scopes and expiry
+    Rotate {
+        /// Label or public key of the key
+        key: String,
+
+        /// Write the new key pair to this password-encrypted key file
+        #[arg(long)]
+        pem: Option<String>,
+
+        /// Print the new secret key in plaintext to stdout
+        #[arg(long)]
+        insecure_print_secret: bool,
+    },
+    /// Authorize a public key generated elsewhere
+    Import {
+        /// Hex secp256k1 public key
+        public_key: String,
+
+        #[arg(short, long)]
+        label: String,
+
+        /// Expiry date (ISO 8601 format, defaults to a year from now)
+        #[arg(short, long)]
+        expiry: Option<String>,
+
+        /// Grant the key every scope
+        #[arg(long, conflicts_with = "scopes")]
+        admin: bool,
+
+        /// Scopes to grant: read, transfer, release, key-admin
+        /// (default: read,transfer)
+        #[arg(long = "scope", value_delimiter = ',')]
+        scopes: Vec<Scope>,
     },
 }
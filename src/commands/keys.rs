@@ -1,17 +1,110 @@
-use chrono::{DateTime, Duration, Utc};
-use dialoguer::{Confirm, Input};
+use std::fs::OpenOptions;
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
 
-use crate::auth;
+use chrono::{DateTime, Duration, SecondsFormat, SubsecRound, Utc};
+use dialoguer::{Confirm, Input, Password};
+use serde::Serialize;
+
+use crate::auth::keys::{AuthorizedKeys, Scope};
 use crate::config::Config;
 use crate::crypto::keypair::KeyPair;
 use crate::error::{DeckForgeError, Result};
 
-/// Command: Generates a new Keypair, writing to a PEM file.
-/// Adds the public key to an authorized_keys file.
-pub fn generate_key(label: Option<String>, expiry: Option<String>, config: &Config) -> Result<()> {
+fn parse_expiry(expiry: &str) -> Result<DateTime<Utc>> {
+    expiry
+        .parse::<DateTime<Utc>>()
+        .map_err(|e| DeckForgeError::Chrono(e.to_string()))
+}
+
+fn default_expiry() -> DateTime<Utc> {
+    Utc::now().trunc_subsecs(0) + Duration::days(365)
+}
+
+/// `--admin` grants every scope; no scopes at all means the default ones.
+fn resolve_scopes(admin: bool, scopes: Vec<Scope>) -> Vec<Scope> {
+    if admin {
+        Scope::ALL.to_vec()
+    } else if scopes.is_empty() {
+        Scope::DEFAULT.to_vec()
+    } else {
+        scopes
+    }
+}
+
+fn scope_names(scopes: &[Scope]) -> String {
+    scopes.iter().map(|scope| scope.name()).collect::<Vec<_>>().join(", ")
+}
+
+/// Environment variable holding the key file password, for scripts.
+const KEY_PASSWORD_ENV: &str = "DECKFORGE_KEY_PASSWORD";
+
+/// The password of a key file: `$DECKFORGE_KEY_PASSWORD` when set, otherwise
+/// prompted for (twice when `confirm`, for a new key file).
+pub fn key_password(confirm: bool) -> Result<String> {
+    if let Ok(password) = std::env::var(KEY_PASSWORD_ENV) {
+        return Ok(password);
+    }
+    let prompt = Password::new().with_prompt("Key file password");
+    let prompt = if confirm {
+        prompt.with_confirmation("Repeat the password", "The passwords do not match")
+    } else {
+        prompt
+    };
+    prompt.interact().map_err(|e| DeckForgeError::Dialoguer(e.to_string()))
+}
+
+/// A new secret key must end up somewhere: in an encrypted key file or, when
+/// explicitly asked for, on stdout.
+fn check_key_output(pem: Option<&str>, insecure_print_secret: bool) -> Result<()> {
+    if pem.is_none() && !insecure_print_secret {
+        return Err(DeckForgeError::MissingKeyOutput);
+    }
+    Ok(())
+}
+
+/// Writes a key pair to a new password-encrypted key file readable only by
+/// its owner.
+fn write_key_file(keypair: &KeyPair, pem_path: &str) -> Result<()> {
+    let contents = keypair.as_encrypted_pem(&key_password(true)?)?;
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(pem_path)?;
+    file.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+fn print_key_pair(keypair: &KeyPair, pem: Option<&str>, insecure_print_secret: bool) {
+    if let Some(pem_path) = pem {
+        println!("Key File: {}", pem_path);
+    }
+    println!("Public Key: {}", keypair.public_key_as_string());
+    if insecure_print_secret {
+        println!("Secret Key: {}", keypair.secret_key_as_string());
+    }
+}
+
+/// Command: Generates a new Keypair, writing it to an encrypted key file
+/// and adding the public key to an authorized_keys file. API clients sign
+/// their requests with the secret key; the public key alone grants nothing.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_key(
+    label: Option<String>,
+    expiry: Option<String>,
+    admin: bool,
+    scopes: Vec<Scope>,
+    pem: Option<String>,
+    insecure_print_secret: bool,
+    yes: bool,
+    config: &Config,
+) -> Result<()> {
+    check_key_output(pem.as_deref(), insecure_print_secret)?;
+    let scopes = resolve_scopes(admin, scopes);
     let keypair = KeyPair::new();
-    let public_key = hex::encode(keypair.public_key);
-    let secret_key = hex::encode(&keypair.secret_key[..]);
+    let public_key = keypair.public_key_as_string();
 
     let label = match label {
         Some(l) => l,
@@ -21,45 +114,162 @@ pub fn generate_key(label: Option<String>, expiry: Option<String>, config: &Conf
             .map_err(|e| DeckForgeError::Dialoguer(e.to_string()))?,
     };
 
-    let default_expiry = Utc::now() + Duration::days(365);
     let expiry: DateTime<Utc> = match expiry {
-        Some(e) => e.parse::<DateTime<Utc>>()
-            .map_err(|e| DeckForgeError::Chrono(e.to_string()))?,
+        Some(e) => parse_expiry(&e)?,
         None => {
             let expiry_str: String = Input::new()
                 .with_prompt("Enter an expiry date for the API key (ISO 8601 format)")
-                .default(default_expiry.to_rfc3339())
+                .default(default_expiry().to_rfc3339())
                 .interact_text()
                 .map_err(|e| DeckForgeError::Dialoguer(e.to_string()))?;
-            expiry_str.parse::<DateTime<Utc>>()
-                .map_err(|e| DeckForgeError::Chrono(e.to_string()))?
+            parse_expiry(&expiry_str)?
         }
     };
 
     let auth_keys_path = config.authorized_keys_path();
-    let mut authorized_keys = auth::keys::AuthorizedKeys::load_from_file(auth_keys_path)
-        .unwrap_or_else(|_| auth::keys::AuthorizedKeys::new());
+    let mut authorized_keys = AuthorizedKeys::load_or_new(auth_keys_path)?;
+    authorized_keys.ensure_unused(&label, &public_key)?;
 
-    if authorized_keys.is_key_authorized(&public_key) {
-        tracing::warn!("Key already exists in authorized_keys file.");
-    } else {
-        let confirm = Confirm::new()
+    if let Some(pem_path) = &pem {
+        write_key_file(&keypair, pem_path)?;
+    }
+
+    let confirm = yes
+        || Confirm::new()
             .with_prompt("Do you want to add this key to the authorized_keys file?")
             .interact()
             .map_err(|e| DeckForgeError::Dialoguer(e.to_string()))?;
 
-        if confirm {
-            authorized_keys.add_key(label.clone(), public_key.clone(), expiry);
-            authorized_keys.save_to_file(auth_keys_path)?;
-            tracing::info!("Key added to authorized_keys file.");
-        } else {
-            tracing::info!("Key not added to authorized_keys file.");
-        }
+    if confirm {
+        authorized_keys.add_key(label.clone(), public_key, expiry, scopes.clone());
+        authorized_keys.save_to_file(auth_keys_path)?;
+        tracing::info!("Key added to authorized_keys file.");
+    } else {
+        tracing::info!("Key not added to authorized_keys file.");
     }
 
-    println!("Public Key: {}", public_key);
-    println!("Secret Key: {}", secret_key);
+    print_key_pair(&keypair, pem.as_deref(), insecure_print_secret);
     println!("Label: {}", label);
     println!("Expiry: {}", expiry.to_rfc3339());
+    println!("Scopes: {}", scope_names(&scopes));
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct KeyListing<'a> {
+    label: &'a str,
+    public_key: &'a str,
+    fingerprint: String,
+    expiry: DateTime<Utc>,
+    expired: bool,
+    scopes: &'a [Scope],
+}
+
+/// Command: Lists the authorized keys with their fingerprints, expiry and scopes.
+pub fn list_keys(as_json: bool, config: &Config) -> Result<()> {
+    let authorized_keys = AuthorizedKeys::load_or_new(config.authorized_keys_path())?;
+    let listings: Vec<KeyListing> = authorized_keys
+        .keys
+        .iter()
+        .map(|key| KeyListing {
+            label: &key.label,
+            public_key: &key.public_key,
+            fingerprint: key.fingerprint(),
+            expiry: key.expiry,
+            expired: key.is_expired(),
+            scopes: &key.scopes,
+        })
+        .collect();
+
+    if as_json {
+        println!("{}", serde_json::to_string_pretty(&listings)?);
+        return Ok(());
+    }
+
+    let label_width = listings.iter().map(|l| l.label.len()).max().unwrap_or(0).max("LABEL".len());
+    println!("{:<label_width$}  {:<16}  {:<20}  {:<7}  SCOPES", "LABEL", "FINGERPRINT", "EXPIRY", "STATUS");
+    for listing in &listings {
+        println!(
+            "{:<label_width$}  {:<16}  {:<20}  {:<7}  {}",
+            listing.label,
+            listing.fingerprint,
+            listing.expiry.to_rfc3339_opts(SecondsFormat::Secs, true),
+            if listing.expired { "expired" } else { "active" },
+            scope_names(listing.scopes)
+        );
+    }
+    Ok(())
+}
+
+/// Command: Removes a key, named by label or public key. A running server
+/// stops accepting it on its next reload of the file.
+pub fn revoke_key(name: &str, config: &Config) -> Result<()> {
+    let auth_keys_path = config.authorized_keys_path();
+    let mut authorized_keys = AuthorizedKeys::load_from_file(auth_keys_path)?;
+    let key = authorized_keys.remove(name)?;
+    authorized_keys.save_to_file(auth_keys_path)?;
+    println!("Revoked: {}", key.describe());
+    Ok(())
+}
+
+/// Command: Moves a key's expiry, which also revives an expired key.
+pub fn extend_key(name: &str, expiry: &str, config: &Config) -> Result<()> {
+    let expiry = parse_expiry(expiry)?;
+    let auth_keys_path = config.authorized_keys_path();
+    let mut authorized_keys = AuthorizedKeys::load_from_file(auth_keys_path)?;
+    let key = authorized_keys.get_mut(name)?;
+    key.expiry = expiry;
+    let description = key.describe();
+    authorized_keys.save_to_file(auth_keys_path)?;
+    println!("Extended: {}", description);
+    println!("Expiry: {}", expiry.to_rfc3339());
+    Ok(())
+}
+
+/// Command: Replaces a key with a freshly generated one under the same label,
+/// scopes and expiry. The old key stops working as soon as the server reloads.
+pub fn rotate_key(name: &str, pem: Option<String>, insecure_print_secret: bool, config: &Config) -> Result<()> {
+    check_key_output(pem.as_deref(), insecure_print_secret)?;
+    let auth_keys_path = config.authorized_keys_path();
+    let mut authorized_keys = AuthorizedKeys::load_from_file(auth_keys_path)?;
+    let keypair = KeyPair::new();
+    let key = authorized_keys.get_mut(name)?;
+    if let Some(pem_path) = &pem {
+        write_key_file(&keypair, pem_path)?;
+    }
+    let old = key.describe();
+    key.public_key = keypair.public_key_as_string();
+    let new = key.describe();
+    authorized_keys.save_to_file(auth_keys_path)?;
+
+    println!("Rotated: {} -> {}", old, new);
+    print_key_pair(&keypair, pem.as_deref(), insecure_print_secret);
+    Ok(())
+}
+
+/// Command: Authorizes a public key generated elsewhere, e.g. by a client
+/// that never shares its secret key.
+pub fn import_key(
+    public_key: &str,
+    label: String,
+    expiry: Option<String>,
+    admin: bool,
+    scopes: Vec<Scope>,
+    config: &Config,
+) -> Result<()> {
+    let public_key = KeyPair::normalize_public_key(public_key)?;
+    let expiry = expiry.as_deref().map(parse_expiry).transpose()?.unwrap_or_else(default_expiry);
+    let scopes = resolve_scopes(admin, scopes);
+
+    let auth_keys_path = config.authorized_keys_path();
+    let mut authorized_keys = AuthorizedKeys::load_or_new(auth_keys_path)?;
+    authorized_keys.ensure_unused(&label, &public_key)?;
+    authorized_keys.add_key(label, public_key, expiry, scopes);
+    authorized_keys.save_to_file(auth_keys_path)?;
+
+    let key = authorized_keys.keys.last().expect("key was just added");
+    println!("Imported: {}", key.describe());
+    println!("Expiry: {}", expiry.to_rfc3339());
+    println!("Scopes: {}", scope_names(&key.scopes));
     Ok(())
 }
@@ -0,0 +1,48 @@
+use std::fs;
+
+use serde_json::json;
+
+use crate::card::series::TradingCardSeries;
+use crate::error::{DeckForgeError, Result};
+
+/// Command: Checks a series file against every release rule without touching
+/// the chain, printing each issue with its JSON path (or all of them as JSON).
+/// Fails when any issue is found so scripts can gate on the exit status.
+pub fn lint_series(series_file: &str, as_json: bool) -> Result<()> {
+    let series_data = fs::read_to_string(series_file)?;
+    let issues = TradingCardSeries::lint_json(&series_data);
+
+    if as_json {
+        let report = json!({
+            "series_file": series_file,
+            "valid": issues.is_empty(),
+            "issues": issues,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else if issues.is_empty() {
+        println!("{}: no issues found", series_file);
+    } else {
+        for issue in &issues {
+            println!("{}: {}", issue.path, issue.message);
+            if let Some(expected) = &issue.expected {
+                println!("    expected: {}", expected);
+            }
+            if let Some(actual) = &issue.actual {
+                println!("    actual:   {}", actual);
+            }
+            if !issue.cards.is_empty() {
+                let numbers: Vec<String> = issue.cards.iter().map(|n| n.to_string()).collect();
+                println!("    cards:    {}", numbers.join(", "));
+            }
+        }
+        println!("{}: {} issue(s) found", series_file, issues.len());
+    }
+
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(DeckForgeError::InvalidSeries {
+            reasons: TradingCardSeries::violated_rules(&issues),
+        })
+    }
+}
@@ -1,2 +1,12 @@
+#[allow(clippy::module_inception)]
 pub mod commands;
-pub mod keys;
\ No newline at end of file
+pub mod client;
+pub mod keys;
+pub mod lint;
+pub mod pack;
+pub mod release;
+pub mod repair;
+pub mod shuffle;
+pub mod simulate;
+pub mod storage;
+pub mod transaction;
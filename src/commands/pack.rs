@@ -0,0 +1,35 @@
+use serde_json::json;
+
+use crate::blockchain::deckchain::DeckChain;
+use crate::card::card::TradingCard;
+use crate::commands::client::post_signed;
+use crate::config::Config;
+use crate::error::Result;
+
+/// Command: Opens a pack of a released series, writing the chain file
+/// directly, and prints the dealt card ids.
+pub fn open_pack(series_id: &str, pack: &str, receiver: &str, config: &Config) -> Result<()> {
+    let mut deckchain = DeckChain::new(config)?;
+    for card in deckchain.do_open_pack(series_id, pack, receiver)? {
+        println!("{}", card.card_id());
+    }
+    Ok(())
+}
+
+/// Command: Opens a pack through a running API server, signed with a key
+/// with the transfer scope, and prints the dealt card ids.
+pub async fn open_pack_on_server(
+    series_id: &str,
+    pack: &str,
+    receiver: &str,
+    server: &str,
+    key_file: Option<String>,
+) -> Result<()> {
+    let request = json!({ "series_id": series_id, "pack_id": pack, "receiver": receiver });
+    let body = post_signed(server, "/packs", key_file, serde_json::to_vec(&request)?).await?;
+    let cards: Vec<TradingCard> = serde_json::from_value(body["cards"].clone())?;
+    for card in cards {
+        println!("{}", card.card_id());
+    }
+    Ok(())
+}
@@ -0,0 +1,20 @@
+use std::fs::read_to_string;
+
+use serde_json::Value;
+
+use crate::commands::client::post_signed;
+use crate::error::Result;
+
+/// Command: Releases a series through a running API server, so the server
+/// stays the only writer of its blockchain file. The request is signed with
+/// a key with the release scope in the given PEM file.
+pub async fn release_to_server(series_file: &str, server: &str, key_file: Option<String>) -> Result<()> {
+    // Sent byte for byte, so the release id matches a local release of the file.
+    let series_data = read_to_string(series_file)?;
+    serde_json::from_str::<Value>(&series_data)?;
+
+    let body = post_signed(server, "/series", key_file, series_data.into_bytes()).await?;
+    println!("Transaction: {}", body["transaction_id"].as_str().unwrap_or_default());
+    println!("Block: {}", body["block_index"]);
+    Ok(())
+}
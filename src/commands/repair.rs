@@ -0,0 +1,20 @@
+use crate::blockchain::deckchain::DeckChain;
+use crate::config::Config;
+use crate::error::Result;
+
+/// Command: Truncates the chain in the data directory back to its last valid
+/// block and rewrites the configured storage, lifting any quarantine.
+pub fn repair_chain(config: &Config) -> Result<()> {
+    let repair = DeckChain::repair(config)?;
+
+    println!("Source: {}", repair.source);
+    println!("Kept Blocks: {}", repair.kept_blocks);
+    println!("Discarded Blocks: {}", repair.discarded_blocks);
+    if let Some(reason) = &repair.reason {
+        println!("First Invalid Block: {}", reason);
+    }
+    if let Some(backup) = &repair.backup {
+        println!("Backup: {}", backup);
+    }
+    Ok(())
+}
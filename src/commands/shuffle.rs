@@ -0,0 +1,48 @@
+use sha3::{Digest, Sha3_256};
+
+use crate::blockchain::deckchain::DeckChain;
+use crate::commands::client::post_signed;
+use crate::config::Config;
+use crate::error::Result;
+
+/// Command: Publishes the private shuffle salt of a released series on chain.
+pub fn reveal_shuffle(series_id: &str, config: &Config) -> Result<()> {
+    let mut deckchain = DeckChain::new(config)?;
+    deckchain.do_reveal_shuffle(series_id)
+}
+
+/// Command: Publishes the shuffle salt through the running API server that
+/// released the series, signed with a key with the release scope.
+pub async fn reveal_shuffle_on_server(series_id: &str, server: &str, key_file: Option<String>) -> Result<()> {
+    let path = format!("/series/{}/reveal", series_id);
+    let body = post_signed(server, &path, key_file, Vec::new()).await?;
+    println!("Transaction: {}", body["transaction_id"].as_str().unwrap_or_default());
+    println!("Block: {}", body["block_index"]);
+    Ok(())
+}
+
+/// Command: Regenerates a series' shuffled deck from the on-chain seed and
+/// revealed salt, printing a digest of the card order (and optionally the order).
+pub fn verify_shuffle(series_id: &str, list: bool, config: &Config) -> Result<()> {
+    let deckchain = DeckChain::new(config)?;
+    let release = deckchain.verify_shuffle(series_id)?;
+
+    let mut hasher = Sha3_256::new();
+    for card in &release.released_cards {
+        hasher.update(card.card_id().as_bytes());
+        hasher.update(b"\n");
+    }
+
+    if list {
+        for (position, card) in release.released_cards.iter().enumerate() {
+            println!("{} {}", position, card.card_id());
+        }
+    }
+
+    println!("Series: {}", release.id);
+    println!("Shuffle Hash: {}", release.shuffle_hash);
+    println!("Cards: {}", release.released_cards.len());
+    println!("Deck Digest: {}", hex::encode(hasher.finalize()));
+    println!("Commitment verified.");
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use rand::Rng;
+
+use crate::card::series::TradingCardSeries;
+use crate::card::simulation::simulate_series;
+use crate::error::Result;
+
+/// Command: Shuffles a series file many times and prints the real odds of each
+/// rarity and special finish, their serial ranges, and expected pulls per pack.
+pub fn simulate(
+    series_file: &str,
+    runs: u32,
+    seed: Option<u64>,
+    pack_sizes: &[u32],
+    as_json: bool,
+) -> Result<()> {
+    let series = TradingCardSeries::from_file(series_file)?;
+    let seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
+    let report = simulate_series(&series, runs, seed, pack_sizes);
+
+    if as_json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("Series: {}", report.series_id);
+    println!("Runs: {} (seed {})", report.runs, report.seed);
+    println!("Cards per deck: {}", report.deck_size);
+
+    println!("\nRarity odds per card:");
+    for rarity in &report.rarities {
+        println!(
+            "  {} {:<12} {:>8} cards  {:>8.4}%",
+            rarity.rarity,
+            rarity.name,
+            rarity.cards,
+            rarity.probability * 100.0
+        );
+    }
+
+    println!("\nSpecial finish odds per card:");
+    for special in &report.specials {
+        println!(
+            "  {:<14} {:>8} cards  {:>8.4}%  serials {}-{}",
+            special.name,
+            special.cards,
+            special.probability * 100.0,
+            special.serial_first,
+            special.serial_last
+        );
+    }
+
+    for odds in report.pack_sizes.iter().chain(report.packs.iter()) {
+        match &odds.pack_id {
+            Some(pack_id) => println!("\nPack '{}' ({} cards, {} dealt):", pack_id, odds.cards, odds.packs),
+            None => println!("\nPacks of {} cards ({} dealt):", odds.cards, odds.packs),
+        }
+        for (rarity, expected) in &odds.expected_rarities {
+            let name = series.rarity_name(*rarity).unwrap_or_default();
+            println!("  {} {:<12} {:>8.3} per pack", rarity, name, expected);
+        }
+        for (special, expected) in &odds.expected_specials {
+            println!("  {:<14} {:>8.3} per pack", special, expected);
+        }
+        println!("  At least one special: {:.2}%", odds.chance_of_special * 100.0);
+    }
+
+    Ok(())
+}
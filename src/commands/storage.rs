@@ -0,0 +1,17 @@
+use crate::blockchain::storage::{self, StorageKind};
+use crate::config::Config;
+use crate::error::Result;
+
+/// Command: Copies the chain from one storage backend to another within the
+/// data directory. The config still has to be pointed at the new backend.
+pub fn migrate_storage(from: Option<StorageKind>, to: StorageKind, config: &Config) -> Result<()> {
+    let from = match from {
+        Some(from) => from,
+        None => config.storage_kind()?,
+    };
+    let blocks = storage::migrate(&config.data_dir, from, to)?;
+
+    println!("Migrated {} blocks from {} to {}.", blocks, from.path_in(&config.data_dir), to.path_in(&config.data_dir));
+    println!("Set storage = \"{}\" in the config to use it.", to);
+    Ok(())
+}
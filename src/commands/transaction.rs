@@ -0,0 +1,24 @@
+use crate::blockchain::deckchain::DeckChain;
+use crate::config::Config;
+use crate::error::Result;
+
+/// Command: Looks up a confirmed transaction by id and prints where it was
+/// confirmed along with its contents.
+pub fn show_tx(id: &str, as_json: bool, config: &Config) -> Result<()> {
+    let deckchain = DeckChain::new(config)?;
+    let record = deckchain.transaction(id)?;
+
+    if as_json {
+        println!("{}", serde_json::to_string_pretty(&record)?);
+        return Ok(());
+    }
+
+    println!("Transaction: {}", record.transaction_id);
+    println!("Type: {}", record.transaction.transaction_type.name());
+    println!("Block: {} ({})", record.block_index, record.block_hash);
+    println!("Position: {}", record.position);
+    println!("Confirmations: {}", record.confirmations);
+    println!("Timestamp: {}", record.timestamp);
+    println!("{}", serde_json::to_string_pretty(&record.transaction)?);
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use serde::Deserialize;
 use std::fs;
 
+use crate::blockchain::storage::StorageKind;
 use crate::error::Result;
 
 #[derive(Clone, Deserialize)]
@@ -8,6 +9,7 @@ pub struct Config {
     pub data_dir: String,
     pub listen_addr: Option<String>,
     pub authorized_keys_path: Option<String>,
+    pub storage: Option<String>,
 }
 
 impl Config {
@@ -28,12 +30,20 @@ impl Config {
         if let Ok(val) = std::env::var("DECKFORGE_AUTH_KEYS_PATH") {
             self.authorized_keys_path = Some(val);
         }
+        if let Ok(val) = std::env::var("DECKFORGE_STORAGE") {
+            self.storage = Some(val);
+        }
     }
 
     pub fn listen_addr(&self) -> &str {
         self.listen_addr.as_deref().unwrap_or("127.0.0.1:3000")
     }
 
+    /// The chain storage backend: `log` (default), `json` or `sqlite`.
+    pub fn storage_kind(&self) -> Result<StorageKind> {
+        self.storage.as_deref().unwrap_or("log").parse()
+    }
+
     pub fn authorized_keys_path(&self) -> &str {
         self.authorized_keys_path.as_deref().unwrap_or("authorized_keys.json")
     }
@@ -1,7 +1,9 @@
-use secp256k1::{PublicKey, Secp256k1, SecretKey};
+use secp256k1::ecdsa::Signature;
+use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
 use secp256k1::rand::rngs::OsRng;
 use pem::{Pem, encode_many, parse_many};
 
+use crate::crypto::keystore;
 use crate::error::{DeckForgeError, Result};
 
 pub struct KeyPair {
@@ -31,6 +33,8 @@ impl KeyPair {
         }
     }
 
+    /// The key pair with the secret key in plaintext. Prefer
+    /// `as_encrypted_pem` for anything written to disk.
     pub fn as_pem(&self) -> String {
         let pem = vec![
             Pem::new("PUBLIC KEY", self.public_key.to_vec()),
@@ -39,6 +43,28 @@ impl KeyPair {
         encode_many(&pem)
     }
 
+    /// The key pair with the secret key encrypted under `password` (see
+    /// `keystore::encrypt`), readable by `from_pem` with the same password.
+    pub fn as_encrypted_pem(&self, password: &str) -> Result<String> {
+        let encrypted = keystore::encrypt(&self.secret_key[..], &self.public_key, password)?;
+        Ok(self.pem_with_encrypted_key(encrypted))
+    }
+
+    /// `as_encrypted_pem` at a lower scrypt cost, to keep tests fast.
+    #[cfg(test)]
+    pub(crate) fn as_encrypted_pem_with_cost(&self, password: &str, log_n: u8) -> Result<String> {
+        let encrypted = keystore::encrypt_with_cost(&self.secret_key[..], &self.public_key, password, log_n)?;
+        Ok(self.pem_with_encrypted_key(encrypted))
+    }
+
+    fn pem_with_encrypted_key(&self, encrypted: Vec<u8>) -> String {
+        let pem = vec![
+            Pem::new("PUBLIC KEY", self.public_key.to_vec()),
+            Pem::new(keystore::ENCRYPTED_KEY_TAG, encrypted),
+        ];
+        encode_many(&pem)
+    }
+
     pub fn from_keys(public_key: &str, secret_key: &str) -> Result<KeyPair> {
         let pub_bytes = hex::decode(public_key)?;
         let pub_array: [u8; 33] = pub_bytes.try_into().map_err(|v: Vec<u8>| {
@@ -52,15 +78,32 @@ impl KeyPair {
         })
     }
 
-    pub fn from_pem(pem: &str) -> Result<KeyPair> {
+    /// The key pair of a hex secret key, deriving its public key.
+    pub fn from_secret_key(secret_key: &str) -> Result<KeyPair> {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&hex::decode(secret_key)?)?;
+        Ok(KeyPair {
+            public_key: PublicKey::from_secret_key(&secp, &secret_key).serialize(),
+            secret_key,
+        })
+    }
+
+    /// Reads a key file written by `as_pem` or `as_encrypted_pem`. The
+    /// password is only used, and then required, for encrypted files.
+    pub fn from_pem(pem: &str, password: Option<&str>) -> Result<KeyPair> {
         let pems = parse_many(pem.as_bytes())?;
         let public_key = pems.first().ok_or(
             DeckForgeError::Pem(pem::PemError::MalformedFraming)
         )?.contents().to_vec();
-        let secret_key_bytes = pems.get(1).ok_or(
+        let secret_pem = pems.get(1).ok_or(
             DeckForgeError::Pem(pem::PemError::MalformedFraming)
-        )?.contents();
-        let secret_key = SecretKey::from_slice(secret_key_bytes)?;
+        )?;
+        let secret_key = if secret_pem.tag() == keystore::ENCRYPTED_KEY_TAG {
+            let password = password.ok_or(DeckForgeError::KeystorePasswordRequired)?;
+            SecretKey::from_slice(&keystore::decrypt(secret_pem.contents(), &public_key, password)?)?
+        } else {
+            SecretKey::from_slice(secret_pem.contents())?
+        };
         let pub_array: [u8; 33] = public_key.try_into().map_err(|v: Vec<u8>| {
             DeckForgeError::InvalidPublicKeyLength { len: v.len() }
         })?;
@@ -71,6 +114,55 @@ impl KeyPair {
         })
     }
 
+    /// Whether a key file holds a password-encrypted secret key.
+    pub fn is_encrypted_pem(pem: &str) -> bool {
+        parse_many(pem.as_bytes())
+            .is_ok_and(|pems| pems.iter().any(|p| p.tag() == keystore::ENCRYPTED_KEY_TAG))
+    }
+
+    /// Signs a 32-byte digest, returning the 65-byte recoverable signature
+    /// (compact signature followed by the recovery id) as hex.
+    pub fn sign_recoverable(&self, digest: [u8; 32]) -> String {
+        let secp = Secp256k1::new();
+        let message = Message::from_digest(digest);
+        let signature = secp.sign_ecdsa_recoverable(&message, &self.secret_key);
+        let (recovery_id, compact) = signature.serialize_compact();
+
+        let mut bytes = compact.to_vec();
+        bytes.push(i32::from(recovery_id) as u8);
+        hex::encode(bytes)
+    }
+
+    /// Signs a 32-byte digest, returning the 64-byte compact ECDSA signature as hex.
+    pub fn sign(&self, digest: [u8; 32]) -> String {
+        let secp = Secp256k1::new();
+        let signature = secp.sign_ecdsa(&Message::from_digest(digest), &self.secret_key);
+        hex::encode(signature.serialize_compact())
+    }
+
+    /// Checks a `sign` signature of `digest` against a hex public key.
+    /// Malformed keys and signatures do not verify.
+    pub fn verify(public_key: &str, digest: [u8; 32], signature: &str) -> bool {
+        let parse = || -> Option<(PublicKey, Signature)> {
+            let public_key = PublicKey::from_slice(&hex::decode(public_key).ok()?).ok()?;
+            let signature = Signature::from_compact(&hex::decode(signature).ok()?).ok()?;
+            Some((public_key, signature))
+        };
+        match parse() {
+            Some((public_key, signature)) => Secp256k1::verification_only()
+                .verify_ecdsa(&Message::from_digest(digest), &signature, &public_key)
+                .is_ok(),
+            None => false,
+        }
+    }
+
+    /// Parses a hex public key, compressed or not, into the compressed hex
+    /// form that signed requests carry.
+    pub fn normalize_public_key(public_key: &str) -> Result<String> {
+        let public_key = PublicKey::from_slice(&hex::decode(public_key)?)?;
+        Ok(hex::encode(public_key.serialize()))
+    }
+
     pub fn secret_key_as_string(&self) -> String {
         self.secret_key.display_secret().to_string()
     }
@@ -96,9 +188,44 @@ mod tests {
         let original_secret_key = keypair.secret_key_as_string();
         let original_public_key = keypair.public_key_as_string();
         let pem = keypair.as_pem();
-        let keypair_from_pem = KeyPair::from_pem(&pem).unwrap();
+        let keypair_from_pem = KeyPair::from_pem(&pem, None).unwrap();
         assert!(keypair_from_pem.validate());
         assert_eq!(keypair_from_pem.secret_key_as_string(), original_secret_key);
         assert_eq!(keypair_from_pem.public_key_as_string(), original_public_key);
     }
+
+    #[test]
+    fn test_encrypted_pem() {
+        let keypair = KeyPair::new();
+        let pem = keypair.as_encrypted_pem_with_cost("correct horse", 4).unwrap();
+        assert!(KeyPair::is_encrypted_pem(&pem));
+        assert!(!KeyPair::is_encrypted_pem(&keypair.as_pem()));
+        assert!(!pem.contains("PRIVATE KEY"));
+
+        let restored = KeyPair::from_pem(&pem, Some("correct horse")).unwrap();
+        assert_eq!(restored.secret_key_as_string(), keypair.secret_key_as_string());
+        assert!(matches!(KeyPair::from_pem(&pem, None), Err(DeckForgeError::KeystorePasswordRequired)));
+        assert!(matches!(KeyPair::from_pem(&pem, Some("wrong")), Err(DeckForgeError::KeystoreDecrypt)));
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let keypair = KeyPair::new();
+        let public_key = keypair.public_key_as_string();
+        let signature = keypair.sign([7u8; 32]);
+        assert!(KeyPair::verify(&public_key, [7u8; 32], &signature));
+        assert!(!KeyPair::verify(&public_key, [8u8; 32], &signature));
+        assert!(!KeyPair::verify(&KeyPair::new().public_key_as_string(), [7u8; 32], &signature));
+        assert!(!KeyPair::verify(&public_key, [7u8; 32], "zz"));
+
+        let restored = KeyPair::from_secret_key(&keypair.secret_key_as_string()).unwrap();
+        assert_eq!(restored.public_key_as_string(), public_key);
+    }
+
+    #[test]
+    fn test_sign_recoverable_length() {
+        let keypair = KeyPair::new();
+        let signature = keypair.sign_recoverable([7u8; 32]);
+        assert_eq!(signature.len(), 130);
+    }
 }
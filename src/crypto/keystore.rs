@@ -0,0 +1,131 @@
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+use crate::error::{DeckForgeError, Result};
+
+/// PEM tag of a password-encrypted secret key, in place of `PRIVATE KEY`.
+pub const ENCRYPTED_KEY_TAG: &str = "DECKFORGE ENCRYPTED KEY";
+
+const FORMAT_VERSION: u8 = 1;
+/// scrypt cost: 2^15 iterations with r = 8 and p = 1, about 32 MiB of memory.
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+/// Largest scrypt parameters accepted from a key file, about 1 GiB with
+/// r = 8. A crafted header could otherwise demand terabytes.
+const MAX_SCRYPT_LOG_N: u8 = 20;
+const MAX_SCRYPT_R: u32 = 16;
+const MAX_SCRYPT_P: u32 = 4;
+/// Version, log2 N, r and p, salt and nonce.
+const HEADER_LEN: usize = 1 + 1 + 4 + 4 + SALT_LEN + NONCE_LEN;
+
+/// Encrypts a secret key with a password.
+///
+/// The scrypt-derived key seals the secret with ChaCha20-Poly1305. The
+/// output is the header (format version, scrypt parameters, salt, nonce)
+/// followed by the ciphertext. The header and `public_key` are authenticated
+/// as associated data, so neither can be swapped without failing decryption.
+pub fn encrypt(secret_key: &[u8], public_key: &[u8], password: &str) -> Result<Vec<u8>> {
+    seal(secret_key, public_key, password, SCRYPT_LOG_N)
+}
+
+/// `encrypt` at a lower scrypt cost, to keep tests fast in debug builds.
+#[cfg(test)]
+pub(crate) fn encrypt_with_cost(secret_key: &[u8], public_key: &[u8], password: &str, log_n: u8) -> Result<Vec<u8>> {
+    seal(secret_key, public_key, password, log_n)
+}
+
+fn seal(secret_key: &[u8], public_key: &[u8], password: &str, log_n: u8) -> Result<Vec<u8>> {
+    if password.is_empty() {
+        return Err(DeckForgeError::EmptyKeystorePassword);
+    }
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    let mut keystore = Vec::with_capacity(HEADER_LEN + secret_key.len() + 16);
+    keystore.push(FORMAT_VERSION);
+    keystore.push(log_n);
+    keystore.extend_from_slice(&SCRYPT_R.to_be_bytes());
+    keystore.extend_from_slice(&SCRYPT_P.to_be_bytes());
+    keystore.extend_from_slice(&salt);
+    keystore.extend_from_slice(&nonce);
+
+    let cipher = cipher(password, &salt, log_n, SCRYPT_R, SCRYPT_P)?;
+    let aad = [&keystore[..], public_key].concat();
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: secret_key, aad: &aad })
+        .map_err(|_| DeckForgeError::KeystoreDecrypt)?;
+    keystore.extend_from_slice(&ciphertext);
+    Ok(keystore)
+}
+
+/// Recovers the secret key sealed by `encrypt` for `public_key`.
+pub fn decrypt(keystore: &[u8], public_key: &[u8], password: &str) -> Result<Vec<u8>> {
+    if keystore.len() <= HEADER_LEN {
+        return Err(DeckForgeError::KeystoreDecrypt);
+    }
+    let (header, ciphertext) = keystore.split_at(HEADER_LEN);
+    if header[0] != FORMAT_VERSION {
+        return Err(DeckForgeError::UnsupportedKeystore {
+            reason: format!("format version {}", header[0]),
+        });
+    }
+    let log_n = header[1];
+    let r = u32::from_be_bytes(header[2..6].try_into().expect("slice of 4 bytes"));
+    let p = u32::from_be_bytes(header[6..10].try_into().expect("slice of 4 bytes"));
+    if log_n > MAX_SCRYPT_LOG_N || r > MAX_SCRYPT_R || p > MAX_SCRYPT_P {
+        return Err(DeckForgeError::UnsupportedKeystore {
+            reason: format!("scrypt parameters log2 N = {}, r = {}, p = {} exceed the limits", log_n, r, p),
+        });
+    }
+    let salt = &header[10..10 + SALT_LEN];
+    let nonce = &header[10 + SALT_LEN..];
+
+    let cipher = cipher(password, salt, log_n, r, p)?;
+    let aad = [header, public_key].concat();
+    cipher
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: &aad })
+        .map_err(|_| DeckForgeError::KeystoreDecrypt)
+}
+
+fn cipher(password: &str, salt: &[u8], log_n: u8, r: u32, p: u32) -> Result<ChaCha20Poly1305> {
+    let params = scrypt::Params::new(log_n, r, p, 32).map_err(|_| DeckForgeError::KeystoreDecrypt)?;
+    let mut key = [0u8; 32];
+    scrypt::scrypt(password.as_bytes(), salt, &params, &mut key).map_err(|_| DeckForgeError::KeystoreDecrypt)?;
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keystore_round_trip() {
+        let secret = [7u8; 32];
+        let public = [2u8; 33];
+        let keystore = encrypt_with_cost(&secret, &public, "hunter2", 4).unwrap();
+        assert!(!keystore.windows(secret.len()).any(|w| w == secret));
+        assert_eq!(decrypt(&keystore, &public, "hunter2").unwrap(), secret);
+
+        assert!(matches!(decrypt(&keystore, &public, "hunter3"), Err(DeckForgeError::KeystoreDecrypt)));
+        assert!(matches!(decrypt(&keystore, &[3u8; 33], "hunter2"), Err(DeckForgeError::KeystoreDecrypt)));
+        let mut tampered = keystore.clone();
+        tampered[1] = 5;
+        assert!(decrypt(&tampered, &public, "hunter2").is_err());
+        tampered[1] = 40;
+        assert!(matches!(
+            decrypt(&tampered, &public, "hunter2"),
+            Err(DeckForgeError::UnsupportedKeystore { .. })
+        ));
+        assert!(matches!(
+            encrypt(&secret, &public, ""),
+            Err(DeckForgeError::EmptyKeystorePassword)
+        ));
+    }
+}
@@ -1,2 +1,3 @@
 pub mod keypair;
+pub mod keystore;
 pub mod wallet;
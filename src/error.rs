@@ -20,6 +20,33 @@ pub enum DeckForgeError {
     #[error("PEM error: {0}")]
     Pem(#[from] pem::PemError),
 
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error("Unknown storage backend '{name}' (expected json, log or sqlite)")]
+    UnknownStorage { name: String },
+
+    #[error("Unknown key scope '{name}' (expected read, transfer, release or key-admin)")]
+    UnknownScope { name: String },
+
+    #[error("No authorized key with label or public key '{name}'")]
+    KeyNotFound { name: String },
+
+    #[error("{count} authorized keys are labelled '{label}'; name the key by its public key")]
+    AmbiguousKeyLabel { label: String, count: usize },
+
+    #[error("An authorized key with label or public key '{name}' already exists")]
+    DuplicateKey { name: String },
+
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Server rejected request ({status}): {message}")]
+    ServerRejected { status: u16, message: String },
+
+    #[error("A signing key is required: pass --key-file or set DECKFORGE_KEY_FILE to a key PEM file")]
+    MissingSigningKey,
+
     #[error("Series '{id}' has already been released")]
     AlreadyReleased { id: String },
 
@@ -29,15 +56,87 @@ pub enum DeckForgeError {
     #[error("No series releases found")]
     NoReleasesFound,
 
+    #[error("Block {index} not found")]
+    BlockNotFound { index: u64 },
+
+    #[error("Block with hash '{hash}' not found")]
+    BlockHashNotFound { hash: String },
+
+    #[error("Transaction '{id}' not found")]
+    UnknownTransaction { id: String },
+
+    #[error("Block {index} has no transaction at position {position}")]
+    TransactionNotFound { index: u64, position: usize },
+
+    #[error("Block {index} predates Merkle roots and has no inclusion proofs")]
+    NoMerkleRoot { index: u64 },
+
     #[error("Blockchain file not found: {path}")]
     BlockchainNotFound { path: String },
 
+    #[error("Blockchain at {path} is corrupt: {reason}")]
+    CorruptChain { path: String, reason: String },
+
+    #[error("Blockchain at {path} is invalid ({reason}) and has been quarantined as {quarantined}; run repair-chain to recover its valid blocks")]
+    ChainQuarantined { path: String, quarantined: String, reason: String },
+
     #[error("Blockchain is empty (no genesis block)")]
     EmptyChain,
 
     #[error("Validation failed: {reason}")]
     Validation { reason: String },
 
+    #[error("Series validation failed: {}", .reasons.join("; "))]
+    InvalidSeries { reasons: Vec<String> },
+
+    #[error("Card '{card_id}' not found")]
+    CardNotFound { card_id: String },
+
+    #[error("Card '{card_id}' has already been minted")]
+    AlreadyMinted { card_id: String },
+
+    #[error("Cannot mint cards for unreleased series '{id}'")]
+    MintUnreleasedSeries { id: String },
+
+    #[error("No shuffle commitment found for series '{id}'")]
+    ShuffleNotCommitted { id: String },
+
+    #[error("Revealed salt for series '{id}' does not match its shuffle commitment")]
+    ShuffleCommitmentMismatch { id: String },
+
+    #[error("Pack '{pack_id}' not found in series '{series_id}'")]
+    PackNotFound { series_id: String, pack_id: String },
+
+    #[error("Not enough undealt cards left in series '{series_id}' for pack '{pack_id}'")]
+    PackSoldOut { series_id: String, pack_id: String },
+
+    #[error("Invalid signature on transfer of card '{card_id}'")]
+    InvalidSignature { card_id: String },
+
+    #[error("Address '{address}' does not own card '{card_id}'")]
+    NotCardOwner { card_id: String, address: String },
+
+    #[error("Invalid receiver address '{address}': expected 0x and 40 lowercase hex digits, not the null address")]
+    InvalidReceiver { address: String },
+
+    #[error("Transfer of card '{card_id}' has nonce {got}, expected {expected}")]
+    InvalidNonce { card_id: String, expected: u64, got: u64 },
+
+    #[error("This key file is encrypted; a password is required to read it")]
+    KeystorePasswordRequired,
+
+    #[error("Could not decrypt the key file: wrong password or corrupted keystore")]
+    KeystoreDecrypt,
+
+    #[error("Unsupported keystore: {reason}")]
+    UnsupportedKeystore { reason: String },
+
+    #[error("Keystore password must not be empty")]
+    EmptyKeystorePassword,
+
+    #[error("The secret key would be lost: pass --pem to save it to an encrypted key file, or --insecure-print-secret")]
+    MissingKeyOutput,
+
     #[error("Invalid public key length: expected 33 bytes, got {len}")]
     #[allow(dead_code)] // used by crypto module which is test-exercised
     InvalidPublicKeyLength { len: usize },
@@ -10,7 +10,7 @@ mod error;
 use clap::Parser;
 
 use crate::api::server;
-use crate::commands::commands::Commands;
+use crate::commands::commands::{Commands, KeysCommand};
 use crate::blockchain::deckchain::DeckChain;
 use crate::config::Config;
 
@@ -32,21 +32,77 @@ async fn main() -> anyhow::Result<()> {
     let config = Config::load(&cli.config)?;
 
     match cli.command {
-        Commands::GenerateKey { label, expiry } => {
-            commands::keys::generate_key(label, expiry, &config)?;
+        Commands::GenerateKey { label, expiry, admin, scopes, pem, insecure_print_secret, yes } => {
+            commands::keys::generate_key(label, expiry, admin, scopes, pem, insecure_print_secret, yes, &config)?;
         }
 
+        Commands::Keys { command } => match command {
+            KeysCommand::List { json } => commands::keys::list_keys(json, &config)?,
+            KeysCommand::Revoke { key } => commands::keys::revoke_key(&key, &config)?,
+            KeysCommand::Extend { key, expiry } => commands::keys::extend_key(&key, &expiry, &config)?,
+            KeysCommand::Rotate { key, pem, insecure_print_secret } => {
+                commands::keys::rotate_key(&key, pem, insecure_print_secret, &config)?
+            }
+            KeysCommand::Import { public_key, label, expiry, admin, scopes } => {
+                commands::keys::import_key(&public_key, label, expiry, admin, scopes, &config)?
+            }
+        },
+
         Commands::StartServer => {
             tracing::info!("Starting server...");
             server::start_server(config).await?;
         }
 
-        Commands::InsertReleaseSet { series_file } => {
+        Commands::InsertReleaseSet { series_file, server: Some(server), key_file } => {
+            commands::release::release_to_server(&series_file, &server, key_file).await?;
+        }
+
+        Commands::InsertReleaseSet { series_file, server: None, .. } => {
             let mut deckchain = DeckChain::new(&config)?;
             if let Err(e) = deckchain.do_release_series(series_file) {
                 tracing::error!("Error: {}", e);
             }
         }
+
+        Commands::LintSeries { series_file, json } => {
+            commands::lint::lint_series(&series_file, json)?;
+        }
+
+        Commands::RepairChain => {
+            commands::repair::repair_chain(&config)?;
+        }
+
+        Commands::ShowTx { id, json } => {
+            commands::transaction::show_tx(&id, json, &config)?;
+        }
+
+        Commands::MigrateStorage { from, to } => {
+            commands::storage::migrate_storage(from, to, &config)?;
+        }
+
+        Commands::Simulate { series_file, runs, seed, pack_sizes, json } => {
+            commands::simulate::simulate(&series_file, runs, seed, &pack_sizes, json)?;
+        }
+
+        Commands::OpenPack { series_id, pack, receiver, server: Some(server), key_file } => {
+            commands::pack::open_pack_on_server(&series_id, &pack, &receiver, &server, key_file).await?;
+        }
+
+        Commands::OpenPack { series_id, pack, receiver, server: None, .. } => {
+            commands::pack::open_pack(&series_id, &pack, &receiver, &config)?;
+        }
+
+        Commands::RevealShuffle { series_id, server: Some(server), key_file } => {
+            commands::shuffle::reveal_shuffle_on_server(&series_id, &server, key_file).await?;
+        }
+
+        Commands::RevealShuffle { series_id, server: None, .. } => {
+            commands::shuffle::reveal_shuffle(&series_id, &config)?;
+        }
+
+        Commands::VerifyShuffle { series_id, list } => {
+            commands::shuffle::verify_shuffle(&series_id, list, &config)?;
+        }
     }
 
     Ok(())